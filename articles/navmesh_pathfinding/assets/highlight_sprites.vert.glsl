@@ -7,6 +7,7 @@ in vec4 in_instance_highlight_color;
 
 uniform vec2 view_position;
 uniform vec2 view_size;
+uniform float view_scale;
 
 out vec4 highlight_color;
 flat out vec4 texcoord_bounds;
@@ -23,11 +24,12 @@ void main() {
         uv_offset.y + (in_position.y * uv_size.y)
     );
 
+    // Flipped sprites have a negative texcoord size, so the bounds are sorted
+    vec2 texcoord_start = in_instance_texcoord.xy;
+    vec2 texcoord_end = in_instance_texcoord.xy + in_instance_texcoord.zw;
     texcoord_bounds = vec4(
-        in_instance_texcoord.x,
-        in_instance_texcoord.y,
-        in_instance_texcoord.x + in_instance_texcoord.z,
-        in_instance_texcoord.y + in_instance_texcoord.w
+        min(texcoord_start, texcoord_end),
+        max(texcoord_start, texcoord_end)
     );
 
     vec2 pos = vec2(
@@ -35,7 +37,7 @@ void main() {
         view_position.y + (in_instance_position.y + (in_position.y * in_instance_position.w))
     );
 
-    pos = ((pos / view_size) * 2.0) - 1.0;
+    pos = (((pos * view_scale) / view_size) * 2.0) - 1.0;
 
     gl_Position = vec4(pos.x, -pos.y, 0.0, 1.0);
 }
@@ -4,11 +4,13 @@ in vec2 in_positions;
 in vec2 in_texcoord;
 
 uniform vec2 view_size;
+uniform vec2 view_position;
+uniform float view_scale;
 
 out vec2 uv;
 
 void main() {
     uv = in_texcoord;
-    vec2 pos = (in_positions / view_size * vec2(2.0)) - vec2(1.0);
+    vec2 pos = (((view_position + in_positions) * view_scale) / view_size * vec2(2.0)) - vec2(1.0);
     gl_Position = vec4(pos.x, -pos.y, 0.0, 1.0);
 }
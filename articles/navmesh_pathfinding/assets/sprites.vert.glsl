@@ -6,6 +6,7 @@ in vec4 in_instance_texcoord;
 
 uniform vec2 view_position;
 uniform vec2 view_size;
+uniform float view_scale;
 
 out vec2 uv;
 
@@ -23,7 +24,7 @@ void main() {
         view_position.y + (in_instance_position.y + (in_position.y * in_instance_position.w))
     );
 
-    pos = ((pos / view_size) * 2.0) - 1.0;
+    pos = (((pos * view_scale) / view_size) * 2.0) - 1.0;
 
     gl_Position = vec4(pos.x, -pos.y, 0.0, 1.0);
 }
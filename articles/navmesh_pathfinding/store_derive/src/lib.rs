@@ -0,0 +1,266 @@
+//! `#[derive(StoreLoad)]` for the demo data structs.
+//!
+//! Each stored field is written in its own tagged section (see `StoreWriter::write_section`) and is found by its name
+//! when loading. The struct must implement `Default`, fields missing from a save keep their default value.
+//! Saves made with another schema are rejected by `StoreReader::new` before the derived code reads them.
+//!
+//! The encoding of a field is selected from its type:
+//!  - `bool`: `write_bool`
+//!  - `Option<Entity>`: `write_entity_option`
+//!  - `Option<T>`: `write_option`
+//!  - `Vec<T>`: `write_array`
+//!  - `String`: `write_str`
+//!  - anything else: `write`
+//!
+//! The schema of `Vec<T>` and `Option<T>` fields is built from `T`, so changing the type of the values changes the schema.
+//!
+//! Field attributes:
+//!  - `#[store(skip)]`: the field is not stored and keeps its default value
+//!  - `#[store(nested)]`: the field implements `StoreLoad`
+//!  - `#[store(default = path)]`: the field is set to `path()` if it's missing from the save
+//!
+//! Struct attributes:
+//!  - `#[store(after_load = path)]`: `path(&mut value) -> Result<(), Error>` is called once the struct is loaded
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, GenericArgument, Ident, Path, PathArguments, Type};
+
+#[derive(Copy, Clone, PartialEq)]
+enum FieldKind {
+    Value,
+    Bool,
+    Option,
+    Entity,
+    Array,
+    String,
+    Nested,
+}
+
+struct StoreField {
+    ident: Ident,
+    ty: Type,
+    kind: FieldKind,
+    default: Option<Path>,
+}
+
+#[proc_macro_derive(StoreLoad, attributes(store))]
+pub fn derive_store_load(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let after_load = parse_struct_attributes(&input)?;
+    let fields = parse_fields(&input)?;
+
+    let store = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let tag = ident.to_string();
+        let write = match field.kind {
+            FieldKind::Value => quote! { writer.write(&self.#ident) },
+            FieldKind::Bool => quote! { writer.write_bool(self.#ident) },
+            FieldKind::Option => quote! { writer.write_option(&self.#ident) },
+            FieldKind::Entity => quote! { writer.write_entity_option(self.#ident) },
+            FieldKind::Array => quote! { writer.write_array(&self.#ident) },
+            FieldKind::String => quote! { writer.write_str(&self.#ident) },
+            FieldKind::Nested => quote! { crate::store::StoreLoad::store(&mut self.#ident, writer) },
+        };
+
+        quote! { writer.write_section(#tag, |writer| #write ); }
+    });
+
+    // Fields with a `default` attribute track if they were found in the save
+    let loaded_flag = |field: &StoreField| format_ident!("loaded_{}", field.ident);
+    let with_default: Vec<&StoreField> = fields.iter().filter(|field| field.default.is_some() ).collect();
+    let loaded_flags = with_default.iter().map(|field| loaded_flag(field) );
+
+    let load = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let ty = &field.ty;
+        let tag = ident.to_string();
+        let read = match field.kind {
+            FieldKind::Value => quote! { reader.try_read()? },
+            FieldKind::Bool => quote! { reader.try_read_bool()? },
+            FieldKind::Option => quote! { reader.try_read_option()? },
+            FieldKind::Entity => quote! { reader.try_read_entity_option()? },
+            FieldKind::Array => quote! { reader.read_array()? },
+            FieldKind::String => quote! { reader.read_str()?.to_string() },
+            FieldKind::Nested => quote! { <#ty as crate::store::StoreLoad>::load(reader)? },
+        };
+
+        let set_loaded = field.default.as_ref().map(|_| {
+            let flag = loaded_flag(field);
+            quote! { #flag = true; }
+        });
+
+        quote! {
+            if tag == const { crate::store::tag(#tag) } {
+                value.#ident = #read;
+                #set_loaded
+                return Ok(true);
+            }
+        }
+    });
+
+    let set_defaults = with_default.iter().map(|field| {
+        let ident = &field.ident;
+        let flag = loaded_flag(field);
+        let default = &field.default;
+        quote! {
+            if !#flag {
+                value.#ident = #default();
+            }
+        }
+    });
+
+    let schema = fields.iter().map(|field| {
+        let ty = &field.ty;
+        let tag = field.ident.to_string();
+        let inner = last_segment(ty)
+            .and_then(|(_, args)| args.first().copied() );
+
+        match (field.kind, inner) {
+            (FieldKind::Nested, _) => quote! { schema.nested::<#ty>(#tag); },
+            (FieldKind::Array, Some(inner)) => quote! { schema.values::<#inner>(#tag, "Vec"); },
+            (FieldKind::Option, Some(inner)) => quote! { schema.values::<#inner>(#tag, "Option"); },
+            (FieldKind::String, _) => quote! { schema.string(#tag); },
+            _ => quote! { schema.field::<#ty>(#tag); },
+        }
+    });
+
+    let after_load = after_load.map(|path| quote! { #path(&mut value)?; } );
+
+    Ok(quote! {
+        impl crate::store::StoreLoad for #name {
+            fn store(&mut self, writer: &mut crate::store::StoreWriter) {
+                #(#store)*
+            }
+
+            fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
+                let mut value = <#name as Default>::default();
+                #(let mut #loaded_flags = false;)*
+
+                reader.read_sections(|reader, tag| {
+                    #(#load)*
+                    Ok(false)
+                })?;
+
+                #(#set_defaults)*
+                #after_load
+
+                Ok(value)
+            }
+
+            fn schema(schema: &mut crate::store::Schema) {
+                #(#schema)*
+            }
+        }
+    })
+}
+
+fn parse_struct_attributes(input: &DeriveInput) -> syn::Result<Option<Path>> {
+    let mut after_load = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("store") ) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("after_load") {
+                after_load = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("Unknown store attribute. Expected `after_load`"))
+            }
+        })?;
+    }
+
+    Ok(after_load)
+}
+
+fn parse_fields(input: &DeriveInput) -> syn::Result<Vec<StoreField>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            Fields::Unit => { return Ok(Vec::new()); },
+            Fields::Unnamed(_) => { return Err(syn::Error::new(input.span(), "StoreLoad can't be derived for tuple structs")); }
+        },
+        _ => { return Err(syn::Error::new(input.span(), "StoreLoad can only be derived for structs")); }
+    };
+
+    let mut out = Vec::with_capacity(fields.len());
+    for field in fields.iter() {
+        let mut skip = false;
+        let mut nested = false;
+        let mut default = None;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("store") ) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("nested") {
+                    nested = true;
+                } else if meta.path.is_ident("default") {
+                    default = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("Unknown store attribute. Expected `skip`, `nested` or `default`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if skip {
+            continue;
+        }
+
+        let kind = match nested {
+            true => FieldKind::Nested,
+            false => field_kind(&field.ty),
+        };
+
+        out.push(StoreField {
+            ident: field.ident.clone().unwrap(),
+            ty: field.ty.clone(),
+            kind,
+            default,
+        });
+    }
+
+    Ok(out)
+}
+
+fn field_kind(ty: &Type) -> FieldKind {
+    let Some((name, args)) = last_segment(ty) else {
+        return FieldKind::Value;
+    };
+
+    match name.as_str() {
+        "bool" => FieldKind::Bool,
+        "String" => FieldKind::String,
+        "Vec" => FieldKind::Array,
+        "Option" => match args.first().and_then(|ty| last_segment(ty)) {
+            Some((inner, _)) if inner == "Entity" => FieldKind::Entity,
+            _ => FieldKind::Option,
+        },
+        _ => FieldKind::Value,
+    }
+}
+
+/// Name and generic types of the last segment of a type path. Ex: `std::vec::Vec<u32>` => `Vec`, [`u32`]
+fn last_segment(ty: &Type) -> Option<(String, Vec<&Type>)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let segment = path.path.segments.last()?;
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Some((segment.ident.to_string(), args))
+}
@@ -1,10 +1,13 @@
 import { fetch_text, fetch_blob, fetch_arraybuffer } from "./helpers";
 import { set_last_error } from "./error";
 
+// Also read by the native builds of the game client, see `wasm_src/src/native.rs`
 const ASSETS_BUNDLE = `
 TEXTURE;atlas;assets/atlas.png;
 TEXTURE;terrain;assets/terrain.png;
 CSV;atlas_sprites;assets/atlas.csv;
+CSV;key_bindings;assets/key_bindings.csv;
+STRINGS;fr;assets/strings_fr.csv;
 SHADER;sprites;assets/sprites.vert.glsl;assets/sprites.frag.glsl;
 SHADER;highlight_sprites;assets/highlight_sprites.vert.glsl;assets/highlight_sprites.frag.glsl;
 SHADER;terrain;assets/terrain.vert.glsl;assets/terrain.frag.glsl;
@@ -84,6 +87,13 @@ export class EngineAssets {
                     asset_loading_promises.push(this.load_csv(name, path));
                     break;
                 }
+                case "STRINGS": {
+                    // Uploaded to the game client with the other csv files
+                    const language = args[1];
+                    const path = args[2];
+                    asset_loading_promises.push(this.load_csv(`strings_${language}`, path));
+                    break;
+                }
                 case "SHADER": {
                     const name = args[1];
                     const vertex_path = args[2];
@@ -9,6 +9,14 @@ export interface GameStartParams {
     max_texture_size: number,
     screen_width: number,
     screen_height: number,
+    // Physical pixels per css pixel
+    pixel_ratio: number,
+    // Gui language. The game client falls back to english if the assets don't define it.
+    language: string,
+    // Record the inputs of the session so they can be replayed to reproduce a bug
+    record_inputs: boolean,
+    // Input recording to replay instead of the user inputs
+    replay: Uint8Array|null,
 }
 
 export class GameUpdates {
@@ -45,6 +53,8 @@ export class GameInterface {
     module: any;
     protocol: any = null;
     reload_count: number = 0;
+    assets: EngineAssets;
+    params: GameStartParams;
 
     free() {
         if (this.instance) { this.instance.free(); }
@@ -71,13 +81,36 @@ export class GameInterface {
     }
 
     start(assets: EngineAssets, params: GameStartParams): boolean {
-        const mod = this.module;
+        this.assets = assets;
+        this.params = params;
 
+        const init = this.create_init(this.module);
+        if (params.replay) {
+            this.instance = this.module.GameClient.replay(init, params.replay);
+        } else {
+            this.instance = this.module.GameClient.initialize(init);
+        }
+
+        if (!this.instance) {
+            set_last_error("Failed to start game client");
+            return false;
+        }
+
+        return true
+    }
+
+    // Assets are not saved with the client, so reloading needs a new init object too
+    private create_init(mod: any): GameClientInit {
+        const assets = this.assets;
+        const params = this.params;
         const initial_data: GameClientInit = mod.GameClientInit.new();
 
         // Config
         initial_data.max_texture_size(params.max_texture_size);
         initial_data.view_size(params.screen_width, params.screen_height);
+        initial_data.pixel_ratio(params.pixel_ratio);
+        initial_data.language(params.language);
+        initial_data.record_inputs(params.record_inputs);
         
         // Assets
         initial_data.set_assets_bundle(assets.bundle);
@@ -87,14 +120,11 @@ export class GameInterface {
         for (const [font_name, font_value] of assets.fonts.entries()) {
             initial_data.upload_bin_asset(font_name, new Uint8Array(font_value));
         } 
-
-        this.instance = mod.GameClient.initialize(initial_data);
-        if (!this.instance) {
-            set_last_error("Failed to start game client");
-            return false;
+        for (const [texture_name, texture] of assets.textures.entries()) {
+            initial_data.texture_size(texture_name, texture.bitmap.width, texture.bitmap.height);
         }
 
-        return true
+        return initial_data;
     }
 
     async reload(): Promise<boolean> {
@@ -106,7 +136,7 @@ export class GameInterface {
             this.module = await import(`${GAME_SRC_PATH}?v=${this.reload_count}`);
             await this.module.default();
 
-            this.instance = this.module.load(saved);
+            this.instance = this.module.load(saved, this.create_init(this.module));
 
             return true;
         } catch (e) {
@@ -115,14 +145,35 @@ export class GameInterface {
         }
     }
 
+    // Saves the scene as text (compressed and base64 encoded)
+    snapshot(): string {
+        const bytes = this.instance.snapshot();
+        return this.module.snapshot_to_text(bytes);
+    }
+
+    // Replaces the scene with a snapshot made by `snapshot`. Returns false if the snapshot is invalid.
+    restore(text: string): boolean {
+        const bytes = this.module.snapshot_from_text(text);
+        if (!bytes) {
+            return false;
+        }
+
+        return this.instance.restore(bytes);
+    }
+
+    // The inputs recorded since the client started. Null if the client doesn't record its inputs.
+    recording(): Uint8Array|null {
+        return this.instance.recording() ?? null;
+    }
+
     updates(): GameUpdates {
         const buffer = this.get_memory();
         const output_index_ptr = this.instance.updates_ptr();
         return new GameUpdates(this.protocol, buffer, output_index_ptr);
     }
 
-    resize(width: number, height: number) {
-        this.instance.resize(width, height);
+    resize(width: number, height: number, pixel_ratio: number) {
+        this.instance.resize(width, height, pixel_ratio);
     }
 
     private get_memory(): ArrayBuffer {
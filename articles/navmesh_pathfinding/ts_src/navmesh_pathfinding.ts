@@ -1,13 +1,28 @@
-import { file_extension } from "./helpers";
+import { file_extension, fetch_arraybuffer } from "./helpers";
 import { GameInterface, GameStartParams } from "./game_interface";
 import { EngineAssets } from "./assets";
 import { Renderer } from "./renderer";
 import { EngineWebSocket, WebSocketMessage } from "./websocket";
 import { set_last_error } from "./error";
 
-const UPDATE_MOUSE_POSITION = 0b001;
-const UPDATE_MOUSE_BUTTONS  = 0b010;
-const UPDATE_KEYS           = 0b100;
+const UPDATE_MOUSE_POSITION = 0b0001;
+const UPDATE_MOUSE_BUTTONS  = 0b0010;
+const UPDATE_KEYS           = 0b0100;
+const UPDATE_MOUSE_WHEEL    = 0b1000;
+const UPDATE_MOUSE_INSIDE   = 0b10000;
+const UPDATE_TOUCH          = 0b100000;
+const UPDATE_MODIFIERS      = 0b1000000;
+const UPDATE_TEXT           = 0b10000000;
+
+// Matches `TouchPhase` in `wasm_src/src/data/touch.rs`
+const TOUCH_START = 0;
+const TOUCH_MOVE = 1;
+const TOUCH_END = 2;
+const TOUCH_CANCEL = 3;
+
+// The scene is saved in the localStorage so it's restored on the next visit
+const SNAPSHOT_KEY = "navmesh_pathfinding_snapshot";
+const AUTOSAVE_INTERVAL = 5000.0;
 
 // Matches `MouseButton` in `game\src\inputs.rs`
 const MOUSE_BUTTON_LEFT = 0;
@@ -17,13 +32,27 @@ const MOUSE_BUTTON_CENTER = 2;
 class GameInput {
     updates: number = 0;
     mouse_position: number[] = [0.0, 0.0];
+    mouse_inside: boolean = false;
 
     // true: button was pressed, false: button was released, null: button state wasn't changed
     left_mouse_button: boolean|null = null;    
     right_mouse_button: boolean|null = null;
     center_mouse_button: boolean|null = null;
 
-    keys: Map<string, boolean> = new Map();
+    // Accumulated wheel delta since the last frame, in pixels
+    mouse_wheel: number[] = [0.0, 0.0];
+
+    // Key events since the last frame, in order. Stored as [code, pressed, repeat]
+    keys: [string, boolean, boolean][] = [];
+
+    // [alt, ctrl, shift, meta]
+    modifiers: boolean[] = [false, false, false, false];
+
+    // Text input since the last frame. Stored as [text, composing, done]
+    text: [string, boolean, boolean][] = [];
+
+    // Touch events since the last frame, in order. Stored as [phase, id, x, y]
+    touches: number[][] = [];
 }
 
 class Engine {
@@ -38,6 +67,12 @@ class Engine {
     reload_client: boolean = false;
     reload: boolean = false;
     exit: boolean = false;
+    last_autosave: number = 0.0;
+
+    // `?record` in the page url records the inputs, see `export_recording`
+    record_inputs: boolean = false;
+    // `?replay=path` in the page url replays an input recording instead of the user inputs
+    replay: Uint8Array|null = null;
 }
 
 //
@@ -59,7 +94,23 @@ function init_handlers(engine: Engine) {
         input_state.updates |= UPDATE_MOUSE_POSITION;
     })
 
+    canvas.addEventListener("mouseenter", () => {
+        input_state.mouse_inside = true;
+        input_state.updates |= UPDATE_MOUSE_INSIDE;
+    })
+
+    canvas.addEventListener("mouseleave", () => {
+        input_state.mouse_inside = false;
+        input_state.updates |= UPDATE_MOUSE_INSIDE;
+    })
+
+    const update_modifiers = (event: KeyboardEvent|MouseEvent) => {
+        input_state.modifiers = [event.altKey, event.ctrlKey, event.shiftKey, event.metaKey];
+        input_state.updates |= UPDATE_MODIFIERS;
+    };
+
     canvas.addEventListener("mousedown", (event) => {
+        update_modifiers(event);
         input_state.updates |= UPDATE_MOUSE_BUTTONS;
 
         if (event.button === 0) { input_state.left_mouse_button = true; }
@@ -79,31 +130,88 @@ function init_handlers(engine: Engine) {
         event.preventDefault();
     })
 
+    canvas.addEventListener("wheel", (event) => {
+        // Normalize line & page based deltas to pixels
+        const scale = event.deltaMode === 1 ? 16.0 : (event.deltaMode === 2 ? canvas.height : 1.0);
+        input_state.mouse_wheel[0] += event.deltaX * scale;
+        input_state.mouse_wheel[1] += event.deltaY * scale;
+        input_state.updates |= UPDATE_MOUSE_WHEEL;
+        event.preventDefault();
+    }, { passive: false })
+
+    const touch_handler = (phase: number) => (event: TouchEvent) => {
+        for (let i = 0; i < event.changedTouches.length; i++) {
+            const touch = event.changedTouches[i];
+            const x = touch.clientX - canvas.offsetLeft;
+            const y = touch.clientY - canvas.offsetTop;
+            input_state.touches.push([phase, touch.identifier, x, y]);
+        }
+
+        input_state.updates |= UPDATE_TOUCH;
+        event.preventDefault();
+    };
+
+    canvas.addEventListener("touchstart", touch_handler(TOUCH_START), { passive: false });
+    canvas.addEventListener("touchmove", touch_handler(TOUCH_MOVE), { passive: false });
+    canvas.addEventListener("touchend", touch_handler(TOUCH_END), { passive: false });
+    canvas.addEventListener("touchcancel", touch_handler(TOUCH_CANCEL), { passive: false });
+
     canvas.addEventListener("contextmenu", (event) => { event.preventDefault(); });
 
     window.addEventListener("keydown", (event) => {
-        if (event.code === "KeyR") {
+        update_modifiers(event);
+
+        // Don't trigger the shortcuts while the user is typing in the gui
+        const typing = engine.game.instance ? engine.game.instance.wants_keyboard_input() : false;
+        if (!typing && event.code === "KeyR") {
             engine.refresh_client = true;
-        } else if (event.code == "Space") {
+        } else if (!typing && event.code == "Space") {
             toggleDemo();   
         }
 
-        input_state.keys.set(event.code, true);
+        input_state.keys.push([event.code, true, event.repeat]);
         input_state.updates |= UPDATE_KEYS;
+
+        // Printable characters. Shortcuts using ctrl or meta are not text.
+        if (!event.isComposing && event.key.length === 1 && !event.ctrlKey && !event.metaKey) {
+            input_state.text.push([event.key, false, false]);
+            input_state.updates |= UPDATE_TEXT;
+        }
+    });
+
+    // Last chance to save the scene before the page is closed
+    document.addEventListener("visibilitychange", () => {
+        if (document.visibilityState == "hidden") {
+            save_snapshot(engine);
+        }
     });
 
     window.addEventListener("keyup", (event) => {
-        // console.log(event.code);
-        input_state.keys.set(event.code, false);
+        update_modifiers(event);
+        input_state.keys.push([event.code, false, false]);
         input_state.updates |= UPDATE_KEYS;
     });
+
+    window.addEventListener("compositionupdate", (event) => {
+        input_state.text.push([event.data, true, false]);
+        input_state.updates |= UPDATE_TEXT;
+    });
+
+    window.addEventListener("compositionend", (event) => {
+        input_state.text.push([event.data, true, true]);
+        input_state.updates |= UPDATE_TEXT;
+    });
 }
 
 function start_client(engine: Engine): boolean {
     const params: GameStartParams = { 
         max_texture_size: engine.renderer.max_texture_size(),
-        screen_width: engine.renderer.canvas.width,
-        screen_height: engine.renderer.canvas.height,
+        screen_width: engine.renderer.canvas.css_width,
+        screen_height: engine.renderer.canvas.css_height,
+        pixel_ratio: engine.renderer.canvas.pixel_ratio,
+        language: navigator.language.split("-")[0],
+        record_inputs: engine.record_inputs,
+        replay: engine.replay,
     };
 
     return engine.game.start(engine.assets, params);
@@ -116,6 +224,10 @@ async function init(): Promise<Engine | null> {
         return null;
     }
 
+    if (!await init_recording(app)) {
+        return null;
+    }
+
     let init_client = app.game.init();
     let init_assets = app.assets.init();
     let [client_ok, assets_ok] = await Promise.all([init_client, init_assets]);
@@ -131,6 +243,11 @@ async function init(): Promise<Engine | null> {
         return null;
     }
 
+    // A replay must start from the recorded state
+    if (!app.replay) {
+        restore_snapshot(app);
+    }
+
     init_handlers(app);
 
     app.ws.open();
@@ -182,9 +299,8 @@ function websocket_messages(engine: Engine) {
 /// Check if the canvas size changed since the last call, and if so run the on resize logic
 function handle_resize(engine: Engine) {
     if (engine.renderer.handle_resize()) {
-        const width = engine.renderer.canvas.width;
-        const height = engine.renderer.canvas.height;
-        engine.game.resize(width, height)
+        const canvas = engine.renderer.canvas;
+        engine.game.resize(canvas.css_width, canvas.css_height, canvas.pixel_ratio)
     }
 }
 
@@ -192,10 +308,20 @@ function game_input_updates(engine: Engine) {
     const inputs = engine.input;
     const game = engine.game.instance;
 
+    // Modifiers first so that they apply to the mouse & keys events of this frame
+    if ((inputs.updates & UPDATE_MODIFIERS) > 0) {
+        const [alt, ctrl, shift, meta] = inputs.modifiers;
+        game.update_modifiers(alt, ctrl, shift, meta);
+    }
+
     if ((inputs.updates & UPDATE_MOUSE_POSITION) > 0) {
         game.update_mouse_position(inputs.mouse_position[0], inputs.mouse_position[1]);
     }
 
+    if ((inputs.updates & UPDATE_MOUSE_INSIDE) > 0) {
+        game.update_mouse_inside(inputs.mouse_inside);
+    }
+
     if ((inputs.updates & UPDATE_MOUSE_BUTTONS) > 0) {
         if (inputs.left_mouse_button !== null) {
             game.update_mouse_buttons(MOUSE_BUTTON_LEFT, inputs.left_mouse_button);
@@ -214,13 +340,43 @@ function game_input_updates(engine: Engine) {
         inputs.center_mouse_button = null;
     }
 
+    if ((inputs.updates & UPDATE_MOUSE_WHEEL) > 0) {
+        game.update_mouse_wheel(inputs.mouse_wheel[0], inputs.mouse_wheel[1]);
+        inputs.mouse_wheel[0] = 0.0;
+        inputs.mouse_wheel[1] = 0.0;
+    }
+
+    if ((inputs.updates & UPDATE_TOUCH) > 0) {
+        for (let [phase, id, x, y] of inputs.touches) {
+            switch (phase) {
+                case TOUCH_START: { game.touch_start(id, x, y); break; }
+                case TOUCH_MOVE: { game.touch_move(id, x, y); break; }
+                case TOUCH_END: { game.touch_end(id, x, y); break; }
+                case TOUCH_CANCEL: { game.touch_cancel(id, x, y); break; }
+            }
+        }
+
+        inputs.touches.length = 0;
+    }
+
     if ((inputs.updates & UPDATE_KEYS) > 0) {
-        for (let entry of inputs.keys.entries()) {
-            game.update_keys(entry[0], entry[1]);
+        for (let [code, pressed, repeat] of inputs.keys) {
+            game.update_keys(code, pressed, repeat);
         }
     }
 
-    inputs.keys.clear();
+    if ((inputs.updates & UPDATE_TEXT) > 0) {
+        for (let [text, composing, done] of inputs.text) {
+            if (composing) {
+                game.update_composition(text, done);
+            } else {
+                game.update_text(text);
+            }
+        }
+    }
+
+    inputs.keys.length = 0;
+    inputs.text.length = 0;
     inputs.updates = 0;
 }
 
@@ -250,6 +406,84 @@ function render(engine: Engine) {
     engine.renderer.render();
 }
 
+//
+// Autosave
+//
+
+function restore_snapshot(engine: Engine) {
+    let snapshot: string|null = null;
+    try {
+        snapshot = localStorage.getItem(SNAPSHOT_KEY);
+    } catch (e) {
+        // Storage can be disabled by the browser privacy settings
+        return;
+    }
+
+    if (snapshot && !engine.game.restore(snapshot)) {
+        // The snapshot was made by an incompatible version of the client
+        localStorage.removeItem(SNAPSHOT_KEY);
+    }
+}
+
+function save_snapshot(engine: Engine) {
+    try {
+        localStorage.setItem(SNAPSHOT_KEY, engine.game.snapshot());
+    } catch (e) {
+        // Storage is disabled or full. Losing the scene on refresh is not worth stopping the demo.
+        console.log(e);
+    }
+}
+
+function autosave(engine: Engine, time: DOMHighResTimeStamp) {
+    if (engine.replay) {
+        return;
+    }
+
+    if (time - engine.last_autosave >= AUTOSAVE_INTERVAL) {
+        save_snapshot(engine);
+        engine.last_autosave = time;
+    }
+}
+
+//
+// Input recording
+//
+
+async function init_recording(engine: Engine): Promise<boolean> {
+    const params = new URLSearchParams(window.location.search);
+    engine.record_inputs = params.has("record");
+
+    const replay_path = params.get("replay");
+    if (replay_path) {
+        const recording = await fetch_arraybuffer(replay_path);
+        if (!recording) {
+            return false;
+        }
+
+        engine.replay = new Uint8Array(recording);
+    }
+
+    // Called from the browser console to attach the recording to a bug report
+    (window as any).export_recording = () => export_recording(engine);
+
+    return true;
+}
+
+function export_recording(engine: Engine) {
+    const recording = engine.game.recording();
+    if (!recording) {
+        console.log("Inputs are not recorded. Add `?record` to the page url to record them.");
+        return;
+    }
+
+    const url = URL.createObjectURL(new Blob([recording], { type: "application/octet-stream" }));
+    const link = document.createElement("a");
+    link.href = url;
+    link.download = "navmesh_pathfinding_inputs.bin";
+    link.click();
+    setTimeout(() => URL.revokeObjectURL(url), 0);
+}
+
 //
 // Reload
 //
@@ -260,6 +494,9 @@ async function reload(engine: Engine) {
         if (!reloaded) {
             set_last_error("Failed to reload wasm module");
             engine.exit = true;
+        } else if (engine.game.instance.needs_init()) {
+            // The save was made by an incompatible version of the client
+            refresh(engine);
         }
     }
 
@@ -284,8 +521,10 @@ function run(engine: Engine) {
         return;
     }
 
-    update(engine, performance.now());
+    const time = performance.now();
+    update(engine, time);
     render(engine);
+    autosave(engine, time);
 
     if (engine.refresh_client) {
         refresh(engine);
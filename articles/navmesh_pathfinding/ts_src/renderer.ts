@@ -6,19 +6,38 @@ const BASE_SPRITES_CAPACITY = 1024 * 2;
 const BASE_SPRITES_HIGHLIGHT_CAPACITY = 512;
 const BASE_TERRAIN_CAPACITY = 1024 * 10;
 const BASE_DEBUG_CAPACITY = 1024;
-const BASE_GUI_CAPACITY = 1024 * 5;
+// Gui texture ids with this bit set reference an asset texture (see `GUI_USER_TEXTURE` in the game client)
+const GUI_USER_TEXTURE = 0x80000000;
 
 class RendererCanvas {
     container: HTMLElement;
     element: HTMLCanvasElement;
+    // Size of the framebuffer in physical pixels
     width: number;
     height: number;
+    // Size of the canvas in css pixels. This is the size used by the game client.
+    css_width: number;
+    css_height: number;
+    pixel_ratio: number;
 
     constructor(container: HTMLElement, element: HTMLCanvasElement) {
         this.container = container;
         this.element = element;
         this.width = 0;
         this.height = 0;
+        this.css_width = 0;
+        this.css_height = 0;
+        this.pixel_ratio = 1.0;
+    }
+
+    resize(css_width: number, css_height: number) {
+        this.pixel_ratio = window.devicePixelRatio || 1.0;
+        this.css_width = css_width;
+        this.css_height = css_height;
+        this.width = Math.round(css_width * this.pixel_ratio);
+        this.height = Math.round(css_height * this.pixel_ratio);
+        this.element.width = this.width;
+        this.element.height = this.height;
     }
 }
 
@@ -73,47 +92,41 @@ class Debug {
     vao: WebGLVertexArrayObject;
 }
 
+/// A gui mesh. Each mesh owns its buffers so it can be replaced without touching the others.
 interface GuiMesh {
+    // Clip rect in gui points: [x1, y1, x2, y2]
     clip: number[];
     texture: WebGLTexture;
     vao: WebGLVertexArrayObject;
+    index: WebGLBuffer;
+    vertex: WebGLBuffer;
     count: number;
-    offset: number;
 }
 
 class Gui {
-    index: WebGLBuffer;
-    index_offset: number;
-    index_capacity: number;
-    vertex: WebGLBuffer;
-    vertex_offset: number;
-    vertex_capacity: number;
     textures: Map<number, WebGLTexture> = new Map();
-    vao_pool_next: number = 0;
-    vao_pool: WebGLVertexArrayObject[] = [];
-    meshes_next: number = 0;
     meshes: GuiMesh[] = [];
 }
 
 class RendererShaders {
     sprites_attributes: number[];  // position, instance_position, instance_texcoord, instance_data
-    sprites_uniforms: WebGLUniformLocation[];  // View position, View size
+    sprites_uniforms: WebGLUniformLocation[];  // View position, View size, View scale
     sprites: WebGLProgram;
 
     highlight_sprites_attributes: number[];  // position, instance_position, instance_texcoord, highlight_color
-    highlight_sprites_uniforms: WebGLUniformLocation[];  // View position, View size
+    highlight_sprites_uniforms: WebGLUniformLocation[];  // View position, View size, View scale
     highlight_sprites: WebGLProgram;
 
     insert_sprites_attributes: number[]; // position, uv
-    insert_sprites_uniforms: WebGLUniformLocation[];  // view_size
+    insert_sprites_uniforms: WebGLUniformLocation[];  // View size, View position, View scale
     insert_sprites: WebGLProgram;
 
     terrain_attributes: number[];  // position, instance_position, instance_texcoord
-    terrain_uniforms: WebGLUniformLocation[];  // View position, View size
+    terrain_uniforms: WebGLUniformLocation[];  // View position, View size, View scale
     terrain: WebGLProgram;
 
     debug_attributes: number[]; // position, color
-    debug_uniforms: WebGLUniformLocation[];  // View position, View size
+    debug_uniforms: WebGLUniformLocation[];  // View position, View size, View scale
     debug: WebGLProgram;
 
     gui_attributes: number[]; // position, texcoord, color
@@ -130,6 +143,8 @@ export class Renderer {
     color: WebGLRenderbuffer;
     depth: WebGLRenderbuffer;
     visible: boolean = false;
+    // Pixel ratio sent by the game client in the last view transform update
+    view_pixel_ratio: number = 1.0;
 
     shaders: RendererShaders = new RendererShaders();
     textures: WebGLTexture[] = [];
@@ -170,7 +185,6 @@ export class Renderer {
         this.setup_highlight_sprites();
         this.setup_other_sprites();
         this.setup_debug();
-        this.setup_gui();
         this.setup_uniforms();
 
         this.visible = true;
@@ -190,7 +204,8 @@ export class Renderer {
         const canvas = this.canvas;
         const display_width  = canvas.container.clientWidth;
         const display_height = canvas.container.clientHeight;
-        if (display_width == canvas.width && display_height == canvas.height) {
+        const pixel_ratio = window.devicePixelRatio || 1.0;
+        if (display_width == canvas.css_width && display_height == canvas.css_height && pixel_ratio == canvas.pixel_ratio) {
             return false;
         }
 
@@ -200,10 +215,7 @@ export class Renderer {
         }
 
         const ctx = this.ctx;
-        canvas.element.width = display_width;
-        canvas.element.height = display_height;
-        canvas.width = display_width;
-        canvas.height = display_height;
+        canvas.resize(display_width, display_height);
 
         ctx.bindFramebuffer(ctx.DRAW_FRAMEBUFFER, this.framebuffer);
         ctx.bindRenderbuffer(ctx.RENDERBUFFER, this.color);
@@ -224,13 +236,23 @@ export class Renderer {
             [this.shaders.insert_sprites, this.shaders.insert_sprites_uniforms[0]],
             [this.shaders.terrain, this.shaders.terrain_uniforms[1]],
             [this.shaders.debug, this.shaders.debug_uniforms[1]],
-            [this.shaders.gui, this.shaders.gui_uniforms[0]],
         ];
 
         for (let [shader, uniform] of size_uniforms) {
             ctx.useProgram(shader);
             ctx.uniform2fv(uniform, size);
         }
+
+        this.update_gui_view_size();
+    }
+
+    /// Gui meshes are in egui points, which are css pixels
+    private update_gui_view_size() {
+        const ctx = this.ctx;
+        const pixel_ratio = this.view_pixel_ratio;
+        const size = new Float32Array([this.canvas.width / pixel_ratio, this.canvas.height / pixel_ratio]);
+        ctx.useProgram(this.shaders.gui);
+        ctx.uniform2fv(this.shaders.gui_uniforms[0], size);
     }
 
     handle_resize(): boolean {
@@ -425,12 +447,15 @@ export class Renderer {
         ctx.bufferSubData(ctx.ARRAY_BUFFER, 0, updates.get_data(vertex_offset, vertex_size));
     }
 
-    private reset_gui() {
+    private update_gui_mesh_count(message: any) {
+        const ctx = this.ctx;
         const gui = this.gui;
-        gui.vao_pool_next = 0;
-        gui.meshes_next = 0;
-        gui.index_offset = 0;
-        gui.vertex_offset = 0;
+        const count = message.count();
+        for (let mesh of gui.meshes.splice(count)) {
+            ctx.deleteVertexArray(mesh.vao);
+            ctx.deleteBuffer(mesh.index);
+            ctx.deleteBuffer(mesh.vertex);
+        }
     }
 
     private update_gui_textures(updates: GameUpdates, message: any) {
@@ -445,7 +470,11 @@ export class Renderer {
 
         const gui = this.gui;
         const texture = gui.textures.get(id);
-        const pixels_data = updates.get_data(pixels_offset, pixels_size);
+        let pixels_data = updates.get_data(pixels_offset, pixels_size);
+        if (message.format() == "Alpha8") {
+            pixels_data = expand_alpha_to_rgba(pixels_data);
+        }
+
         if (!texture) {
             gui.textures.set(id, create_texture_rgba_from_bytes(ctx, width, height, pixels_data));
         } else {
@@ -454,108 +483,99 @@ export class Renderer {
         }
     }
 
-    private update_gui_mesh(updates: GameUpdates, message: any) {
-        function next_vao(ctx: WebGL2RenderingContext, gui: Gui): WebGLVertexArrayObject {
-            const vao_index = gui.vao_pool_next;
-            if (vao_index >= gui.vao_pool.length) {
-                gui.vao_pool.push(ctx.createVertexArray());
-            }
-            gui.vao_pool_next += 1;
-            return gui.vao_pool[vao_index];
-        }
-
-        function upload_data(ctx: WebGL2RenderingContext, gui: Gui, index: ArrayBuffer, vertex: ArrayBuffer) {
-            if (gui.index_offset + index.byteLength > gui.index_capacity) {
-                const new_capacity = index.byteLength + ((BASE_GUI_CAPACITY * 1.5) | 0);
-                
-                gui.index = realloc_buffer(ctx, gui.index, ctx.ELEMENT_ARRAY_BUFFER, gui.index_capacity, new_capacity, true);
-                gui.index_capacity = new_capacity;
-            }
-
-            if (gui.vertex_offset + vertex.byteLength > gui.vertex_capacity) {
-                const new_capacity = (gui.vertex_offset + vertex.byteLength) + BASE_GUI_CAPACITY;
-                gui.vertex = realloc_buffer(ctx, gui.vertex, ctx.ARRAY_BUFFER, gui.vertex_capacity, new_capacity, true);
-                gui.vertex_capacity = new_capacity;
-            }
-
-            ctx.bindBuffer(ctx.ELEMENT_ARRAY_BUFFER, gui.index);
-            ctx.bufferSubData(ctx.ELEMENT_ARRAY_BUFFER, gui.index_offset, index);
-
-            ctx.bindBuffer(ctx.ARRAY_BUFFER, gui.vertex);
-            ctx.bufferSubData(ctx.ARRAY_BUFFER, gui.vertex_offset, vertex);
-
-            gui.index_offset += index.byteLength;
-            gui.vertex_offset += vertex.byteLength;
+    private free_gui_texture(message: any) {
+        const id = message.id();
+        const texture = this.gui.textures.get(id);
+        if (texture) {
+            this.ctx.deleteTexture(texture);
+            this.gui.textures.delete(id);
         }
+    }
 
-        function build_vao(ctx: WebGL2RenderingContext, gui: Gui, shaders: RendererShaders, vao: WebGLVertexArrayObject, vertex_offset: number) {
+    private update_gui_mesh(updates: GameUpdates, message: any) {
+        function build_mesh(ctx: WebGL2RenderingContext, shaders: RendererShaders): GuiMesh {
             const VERTEX_SIZE = 20;
 
-            ctx.bindVertexArray(vao);
-            ctx.bindBuffer(ctx.ELEMENT_ARRAY_BUFFER, gui.index);
+            const mesh = {
+                clip: [0, 0, 0, 0],
+                texture: null as any,
+                vao: ctx.createVertexArray(),
+                index: ctx.createBuffer(),
+                vertex: ctx.createBuffer(),
+                count: 0,
+            };
+
+            ctx.bindVertexArray(mesh.vao);
+            ctx.bindBuffer(ctx.ELEMENT_ARRAY_BUFFER, mesh.index);
 
             // Vertex data
             let [position, texcoord, color] = shaders.gui_attributes;
-            ctx.bindBuffer(ctx.ARRAY_BUFFER, gui.vertex);
+            ctx.bindBuffer(ctx.ARRAY_BUFFER, mesh.vertex);
             ctx.enableVertexAttribArray(position);
-            ctx.vertexAttribPointer(position, 2, ctx.FLOAT, false, VERTEX_SIZE, vertex_offset);
+            ctx.vertexAttribPointer(position, 2, ctx.FLOAT, false, VERTEX_SIZE, 0);
 
             ctx.enableVertexAttribArray(texcoord);
-            ctx.vertexAttribPointer(texcoord, 2, ctx.FLOAT, false, VERTEX_SIZE, vertex_offset+8);
+            ctx.vertexAttribPointer(texcoord, 2, ctx.FLOAT, false, VERTEX_SIZE, 8);
 
             ctx.enableVertexAttribArray(color);
-            ctx.vertexAttribPointer(color, 4, ctx.UNSIGNED_BYTE, true, VERTEX_SIZE, vertex_offset+16);
+            ctx.vertexAttribPointer(color, 4, ctx.UNSIGNED_BYTE, true, VERTEX_SIZE, 16);
 
             ctx.bindVertexArray(null);
-        }
 
-        function next_mesh(gui: Gui): GuiMesh {
-            const index = gui.meshes_next;
-            if (index >= gui.meshes.length) {
-                gui.meshes[index] = {} as any;
-            }
-            gui.meshes_next += 1;
-            return gui.meshes[index];
+            return mesh;
         }
         
         const ctx = this.ctx;
         const gui = this.gui;
 
-        const [x1, y1, x2, y2] = message.clip();
-        const canvas_height = this.canvas.height;
+        const slot = message.slot();
+        if (slot >= gui.meshes.length) {
+            gui.meshes[slot] = build_mesh(ctx, this.shaders);
+        }
 
         const index_data = updates.get_data(message.index_offset_bytes(), message.index_size_bytes());
         const vertex_data = updates.get_data(message.vertex_offset_bytes(), message.vertex_size_bytes());
-        const texture = gui.textures.get(message.texture_id()) as WebGLTexture;
-        const vertex_offset = gui.vertex_offset;
-        const index_offset = gui.index_offset;
-        const vao = next_vao(ctx, gui);
-        const mesh = next_mesh(gui);
+        const texture_id = message.texture_id();
+        const texture = texture_id >= GUI_USER_TEXTURE ?
+            this.textures[texture_id - GUI_USER_TEXTURE] :
+            gui.textures.get(texture_id) as WebGLTexture;
 
-        ctx.bindVertexArray(vao);
-        upload_data(ctx, gui, index_data, vertex_data);
-        build_vao(ctx, gui, this.shaders, vao, vertex_offset);
+        const mesh = gui.meshes[slot];
+
+        ctx.bindVertexArray(null);
+        ctx.bindBuffer(ctx.ELEMENT_ARRAY_BUFFER, mesh.index);
+        ctx.bufferData(ctx.ELEMENT_ARRAY_BUFFER, index_data, ctx.DYNAMIC_DRAW);
+        ctx.bindBuffer(ctx.ARRAY_BUFFER, mesh.vertex);
+        ctx.bufferData(ctx.ARRAY_BUFFER, vertex_data, ctx.DYNAMIC_DRAW);
 
-        mesh.clip = [x1, canvas_height-y2, x2-x1, canvas_height-y1];
+        mesh.clip = message.clip();
         mesh.texture = texture;
-        mesh.vao = vao;
         mesh.count = message.count();
-        mesh.offset = index_offset;
     }
 
-    private update_view_offset(message: any) {
+    private update_view_transform(message: any) {
         const ctx = this.ctx;
-        const offset = new Float32Array(message); // message is the [x, y] view offset
-        const offset_uniforms: [WebGLProgram, WebGLUniformLocation][] = [
-            [this.shaders.sprites, this.shaders.sprites_uniforms[0]],
-            [this.shaders.highlight_sprites, this.shaders.highlight_sprites_uniforms[0]],
-            [this.shaders.terrain, this.shaders.terrain_uniforms[0]],
-            [this.shaders.debug, this.shaders.debug_uniforms[0]],
+        const offset = new Float32Array(message.offset());
+        const pixel_ratio = message.pixel_ratio();
+        // World units are scaled to physical pixels
+        const scale = message.scale() * pixel_ratio;
+        const transform_uniforms: [WebGLProgram, WebGLUniformLocation, WebGLUniformLocation][] = [
+            [this.shaders.sprites, this.shaders.sprites_uniforms[0], this.shaders.sprites_uniforms[2]],
+            [this.shaders.highlight_sprites, this.shaders.highlight_sprites_uniforms[0], this.shaders.highlight_sprites_uniforms[2]],
+            [this.shaders.terrain, this.shaders.terrain_uniforms[0], this.shaders.terrain_uniforms[2]],
+            [this.shaders.debug, this.shaders.debug_uniforms[0], this.shaders.debug_uniforms[2]],
+            [this.shaders.insert_sprites, this.shaders.insert_sprites_uniforms[1], this.shaders.insert_sprites_uniforms[2]],
         ];
 
-        for (let [shader, uniform] of offset_uniforms) {
+        for (let [shader, offset_uniform, scale_uniform] of transform_uniforms) {
             ctx.useProgram(shader);
-            ctx.uniform2fv(uniform, offset);
+            ctx.uniform2fv(offset_uniform, offset);
+            ctx.uniform1f(scale_uniform, scale);
+        }
+
+        if (pixel_ratio != this.view_pixel_ratio) {
+            this.view_pixel_ratio = pixel_ratio;
+            this.update_gui_view_size();
         }
     }
 
@@ -605,20 +625,24 @@ export class Renderer {
                     this.draw_debug(updates, message.draw_debug())
                     break;
                 }
-                case "ResetGui": {
-                    this.reset_gui();
+                case "GuiMeshCount": {
+                    this.update_gui_mesh_count(message.gui_mesh_count());
                     break;
                 }
                 case "GuiTextureUpdate": {
                     this.update_gui_textures(updates, message.gui_texture_update());
                     break;
                 }
+                case "GuiTextureFree": {
+                    this.free_gui_texture(message.gui_texture_free());
+                    break;
+                }
                 case "GuiMeshUpdate": {
                     this.update_gui_mesh(updates, message.gui_mesh_update());
                     break;
                 }
-                case "UpdateViewOffset": {
-                    this.update_view_offset(message.update_view_offset());
+                case "UpdateViewTransform": {
+                    this.update_view_transform(message.update_view_transform());
                     break;
                 }
                 default: {
@@ -709,12 +733,16 @@ export class Renderer {
             ctx.enable(ctx.SCISSOR_TEST);
             ctx.useProgram(this.shaders.gui);
 
+            // Clip rects are in gui points, the scissor is in framebuffer pixels
+            const pixel_ratio = this.view_pixel_ratio;
+            const canvas_height = this.canvas.height;
+
             for (let mesh of gui.meshes) {
-                let [x, y, width, height] = mesh.clip;
-                ctx.scissor(x, y, width, height);
+                const [x1, y1, x2, y2] = mesh.clip.map((value: number) => value * pixel_ratio);
+                ctx.scissor(x1, canvas_height-y2, x2-x1, y2-y1);
                 ctx.bindTexture(ctx.TEXTURE_2D, mesh.texture);
                 ctx.bindVertexArray(mesh.vao);
-                ctx.drawElementsInstanced(ctx.TRIANGLES, mesh.count, ctx.UNSIGNED_INT, mesh.offset, 1);
+                ctx.drawElementsInstanced(ctx.TRIANGLES, mesh.count, ctx.UNSIGNED_INT, 0, 1);
             }
 
             ctx.disable(ctx.SCISSOR_TEST);
@@ -758,10 +786,7 @@ export class Renderer {
         }
     
         this.canvas = new RendererCanvas(demo, canvas_elem);
-        this.canvas.element.width = demo.clientWidth;
-        this.canvas.element.height = demo.clientHeight;
-        this.canvas.width = demo.clientWidth;
-        this.canvas.height = demo.clientHeight;
+        this.canvas.resize(demo.clientWidth, demo.clientHeight);
 
         return true;
     }
@@ -852,7 +877,7 @@ export class Renderer {
 
         const sprites = build_shader(ctx, assets, "sprites",
             ["in_position", "in_instance_position", "in_instance_texcoord"],
-            ["view_position", "view_size"]
+            ["view_position", "view_size", "view_scale"]
         );
         if (sprites) {
             shaders.sprites = sprites.program;
@@ -864,7 +889,7 @@ export class Renderer {
 
         const highlight_sprites = build_shader(ctx, assets, "highlight_sprites",
             ["in_position", "in_instance_position", "in_instance_texcoord", "in_instance_highlight_color"],
-            ["view_position", "view_size"]
+            ["view_position", "view_size", "view_scale"]
         );
         if (highlight_sprites) {
             shaders.highlight_sprites = highlight_sprites.program;
@@ -876,7 +901,7 @@ export class Renderer {
 
         const insert_sprites = build_shader(ctx, assets, "insert_sprite",
             ["in_positions", "in_texcoord"],
-            ["view_size"]
+            ["view_size", "view_position", "view_scale"]
         );
         if (insert_sprites) {
             shaders.insert_sprites = insert_sprites.program;
@@ -888,7 +913,7 @@ export class Renderer {
 
         const terrain = build_shader(ctx, assets, "terrain",
             ["in_position", "in_instance_position", "in_instance_texcoord"],
-            ["view_position", "view_size"]
+            ["view_position", "view_size", "view_scale"]
         );
         if (terrain) {
             shaders.terrain = terrain.program;
@@ -900,7 +925,7 @@ export class Renderer {
 
         const debug = build_shader(ctx, assets, "debug",
             ["in_position", "in_color"],
-            ["view_position", "view_size"]
+            ["view_position", "view_size", "view_scale"]
         );
         if (debug) {
             shaders.debug = debug.program;
@@ -1083,62 +1108,44 @@ export class Renderer {
         this.setup_debug_vao();
     }
    
-    private setup_gui() {
-        const ctx = this.ctx;
-        const gui = this.gui;
-        
-        gui.index = ctx.createBuffer();
-        gui.index_offset = 0;
-        gui.index_capacity = BASE_GUI_CAPACITY;
-
-        gui.vertex = ctx.createBuffer();
-        gui.vertex_offset = 0;
-        gui.vertex_capacity = BASE_GUI_CAPACITY;
-
-        ctx.bindVertexArray(null);
-        ctx.bindBuffer(ctx.ELEMENT_ARRAY_BUFFER, gui.index);
-        ctx.bufferData(ctx.ELEMENT_ARRAY_BUFFER, gui.index_capacity, ctx.DYNAMIC_DRAW);
-
-        ctx.bindBuffer(ctx.ARRAY_BUFFER, gui.vertex);
-        ctx.bufferData(ctx.ARRAY_BUFFER, gui.vertex_capacity, ctx.DYNAMIC_DRAW);
-
-        for (let i = 0; i < 4; i+=1) {
-            this.gui.vao_pool.push(ctx.createVertexArray());
-        }
-    }
-
     private setup_uniforms() {
         const ctx = this.ctx;
         const position = new Float32Array([0.0, 0.0]);
         const size = new Float32Array([this.canvas.width, this.canvas.height]);
+        const scale = this.canvas.pixel_ratio;
 
-        let [view_position, view_size] = this.shaders.sprites_uniforms;
+        let [view_position, view_size, view_scale] = this.shaders.sprites_uniforms;
         ctx.useProgram(this.shaders.sprites);
         ctx.uniform2fv(view_position, position);
         ctx.uniform2fv(view_size, size);
+        ctx.uniform1f(view_scale, scale);
 
-        [view_position, view_size] = this.shaders.highlight_sprites_uniforms;
+        [view_position, view_size, view_scale] = this.shaders.highlight_sprites_uniforms;
         ctx.useProgram(this.shaders.highlight_sprites);
         ctx.uniform2fv(view_position, position);
         ctx.uniform2fv(view_size, size);
+        ctx.uniform1f(view_scale, scale);
 
-        [view_position, view_size] = this.shaders.terrain_uniforms;
+        [view_position, view_size, view_scale] = this.shaders.terrain_uniforms;
         ctx.useProgram(this.shaders.terrain);
         ctx.uniform2fv(view_position, position);
         ctx.uniform2fv(view_size, size);
+        ctx.uniform1f(view_scale, scale);
 
-        [view_position, view_size] = this.shaders.debug_uniforms;
+        [view_position, view_size, view_scale] = this.shaders.debug_uniforms;
         ctx.useProgram(this.shaders.debug);
         ctx.uniform2fv(view_position, position);
         ctx.uniform2fv(view_size, size);
+        ctx.uniform1f(view_scale, scale);
 
-        view_size = this.shaders.gui_uniforms[0];
-        ctx.useProgram(this.shaders.gui);
-        ctx.uniform2fv(view_size, size);
+        this.view_pixel_ratio = this.canvas.pixel_ratio;
+        this.update_gui_view_size();
 
-        view_size = this.shaders.insert_sprites_uniforms[0];
+        [view_size, view_position, view_scale] = this.shaders.insert_sprites_uniforms;
         ctx.useProgram(this.shaders.insert_sprites);
         ctx.uniform2fv(view_size, size);
+        ctx.uniform2fv(view_position, position);
+        ctx.uniform1f(view_scale, scale);
     }
 }
 
@@ -1241,6 +1248,21 @@ function create_texture_rgba(ctx: WebGL2RenderingContext, cpu_texture: Texture):
     return texture;
 }
 
+/// Font textures are sent as coverage only. Expand them to premultiplied white.
+function expand_alpha_to_rgba(data: ArrayBuffer): ArrayBuffer {
+    const alpha = new Uint8Array(data);
+    const rgba = new Uint8Array(alpha.length * 4);
+    for (let i = 0; i < alpha.length; i += 1) {
+        const a = alpha[i];
+        const offset = i * 4;
+        rgba[offset] = a;
+        rgba[offset+1] = a;
+        rgba[offset+2] = a;
+        rgba[offset+3] = a;
+    }
+    return rgba.buffer;
+}
+
 function create_texture_rgba_from_bytes(ctx: WebGL2RenderingContext, width: number, height: number, data: ArrayBuffer): WebGLTexture {
     const texture = ctx.createTexture();
     ctx.bindTexture(ctx.TEXTURE_2D, texture);
@@ -0,0 +1,283 @@
+//! Runs the game client without a browser and prints the messages sent to the engine.
+//!
+//! Usage: `headless [--frames N] [--script PATH] [--state NAME] [--seed N] [--view WIDTHxHEIGHT] [--pixel-ratio R] [--language LANG] [--dump all|last|none]
+//! [--record PATH] [--replay PATH]`
+//!
+//! `--state` starts the client on the init scene of a demo state (generation, navigation, obstacles or final_demo).
+//! `--record` saves the inputs of the run in an input recording. `--replay` runs all the frames of an input recording
+//! (from the page or from `--record`) instead of the script, and fails if the state of the client diverges from the recording.
+//!
+//! A script is a list of inputs, one per line: `frame command args...`. Inputs are sent before the update of `frame`,
+//! in the order of the script. Empty lines and lines starting with `#` are ignored.
+//!
+//! ```text
+//! # frame command args
+//! 5 mouse_position 300 200
+//! 5 mouse_button 0 pressed
+//! 6 mouse_button 0 released
+//! 8 key KeyD pressed
+//! 9 key KeyD released
+//! 10 modifiers alt ctrl shift meta
+//! 11 wheel 0 -120
+//! 12 touch start 0 300 200
+//! 13 text hello
+//! 14 resize 800 600 2
+//! ```
+use std::process::ExitCode;
+use navmesh_pathfinding_demo::{native, GameClient, GameClientInit};
+
+/// Same rate as `requestAnimationFrame` on a 60Hz screen
+const FRAME_TIME: f64 = 1000.0 / 60.0;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Dump {
+    All,
+    Last,
+    None,
+}
+
+struct Options {
+    frames: u32,
+    script: Option<String>,
+    state: Option<String>,
+    seed: Option<u32>,
+    view: [f32; 2],
+    pixel_ratio: f32,
+    language: Option<String>,
+    dump: Dump,
+    record: Option<String>,
+    replay: Option<String>,
+}
+
+struct ScriptInput {
+    frame: u32,
+    line: usize,
+    command: String,
+    args: Vec<String>,
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let options = parse_options(std::env::args().skip(1))?;
+    let script = match options.script.as_ref() {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read script {:?}: {}", path, e) )?;
+            parse_script(&text)?
+        },
+        None => Vec::new(),
+    };
+
+    let mut init = GameClientInit::new();
+    init.view_size(options.view[0], options.view[1]);
+    init.pixel_ratio(options.pixel_ratio);
+    init.record_inputs(options.record.is_some());
+    if let Some(seed) = options.seed {
+        init.seed(seed);
+    }
+    if let Some(language) = options.language.as_ref() {
+        init.language(language.clone());
+    }
+
+    native::load_assets(&mut init, &native::demo_dir())
+        .map_err(|e| format!("Failed to load the assets: {}", e) )?;
+
+    if let Some(path) = options.replay.as_ref() {
+        return replay(init, path, options.dump);
+    }
+
+    let mut client = GameClient::initialize(init)
+        .ok_or_else(|| "Failed to initialize the game client".to_string() )?;
+
+    if let Some(state) = options.state.as_ref() {
+        if !native::init_demo_state(&mut client, state) {
+            return Err(format!("Unknown demo state {:?}. Demo states are {:?}", state, native::DEMO_STATES));
+        }
+    }
+
+    let mut inputs = script.iter().peekable();
+    for frame in 0..options.frames {
+        while let Some(input) = inputs.next_if(|input| input.frame == frame ) {
+            send_input(&mut client, input)?;
+        }
+
+        client.update(frame as f64 * FRAME_TIME);
+
+        let last = frame + 1 == options.frames;
+        dump_frame(&client, frame, last, options.dump);
+    }
+
+    if let Some(input) = inputs.next() {
+        eprintln!("Inputs after frame {} were not sent (script line {})", options.frames, input.line);
+    }
+
+    if let (Some(path), Some(recording)) = (options.record.as_ref(), client.recording()) {
+        std::fs::write(path, recording)
+            .map_err(|e| format!("Failed to write the recording {:?}: {}", path, e) )?;
+    }
+
+    Ok(())
+}
+
+fn replay(init: GameClientInit, path: &str, dump: Dump) -> Result<(), String> {
+    let recording = std::fs::read(path)
+        .map_err(|e| format!("Failed to read the recording {:?}: {}", path, e) )?;
+
+    let mut client = GameClient::replay(init, recording.into_boxed_slice())
+        .ok_or_else(|| "Failed to start the replay".to_string() )?;
+
+    // The time is read from the recording
+    let mut frame = 0;
+    while !client.replay_finished() {
+        client.update(0.0);
+        dump_frame(&client, frame, client.replay_finished(), dump);
+        frame += 1;
+    }
+
+    let mismatches = client.replay_mismatches();
+    eprintln!("Replayed {} frames, {} checkpoints, {} mismatches", frame, client.replay_checkpoints(), mismatches);
+    match mismatches {
+        0 => Ok(()),
+        _ => Err("The replay diverged from the recording".to_string())
+    }
+}
+
+fn dump_frame(client: &GameClient, frame: u32, last: bool, dump: Dump) {
+    let dump = match dump {
+        Dump::All => true,
+        Dump::Last => last,
+        Dump::None => false,
+    };
+
+    if dump {
+        println!("== frame {} ==", frame);
+        print!("{}", native::dump_output(client));
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item=String>) -> Result<Options, String> {
+    let mut options = Options {
+        frames: 60,
+        script: None,
+        state: None,
+        seed: None,
+        view: [1280.0, 720.0],
+        pixel_ratio: 1.0,
+        language: None,
+        dump: Dump::All,
+        record: None,
+        replay: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}", arg) );
+        match arg.as_str() {
+            "--frames" => { options.frames = parse(&value()?)?; },
+            "--script" => { options.script = Some(value()?); },
+            "--state" => { options.state = Some(value()?); },
+            "--seed" => { options.seed = Some(parse(&value()?)?); },
+            "--pixel-ratio" => { options.pixel_ratio = parse(&value()?)?; },
+            "--language" => { options.language = Some(value()?); },
+            "--record" => { options.record = Some(value()?); },
+            "--replay" => { options.replay = Some(value()?); },
+            "--view" => {
+                let view = value()?;
+                let (width, height) = view.split_once('x')
+                    .ok_or_else(|| format!("View size must be WIDTHxHEIGHT, got {:?}", view) )?;
+                options.view = [parse(width)?, parse(height)?];
+            },
+            "--dump" => {
+                options.dump = match value()?.as_str() {
+                    "all" => Dump::All,
+                    "last" => Dump::Last,
+                    "none" => Dump::None,
+                    other => { return Err(format!("Unknown dump mode {:?}", other)); }
+                };
+            },
+            other => { return Err(format!("Unknown argument {:?}", other)); }
+        }
+    }
+
+    Ok(options)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("Invalid value {:?}", value) )
+}
+
+fn parse_script(text: &str) -> Result<Vec<ScriptInput>, String> {
+    let mut inputs = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let frame = words.next().unwrap_or_default();
+        let command = words.next()
+            .ok_or_else(|| format!("Script line {}: missing command", index + 1) )?;
+
+        inputs.push(ScriptInput {
+            frame: parse(frame).map_err(|e| format!("Script line {}: {}", index + 1, e) )?,
+            line: index + 1,
+            command: command.to_string(),
+            args: words.map(|word| word.to_string() ).collect(),
+        });
+    }
+
+    // Inputs of the same frame keep the order of the script
+    inputs.sort_by_key(|input| input.frame );
+
+    Ok(inputs)
+}
+
+fn send_input(client: &mut GameClient, input: &ScriptInput) -> Result<(), String> {
+    let error = |message: String| format!("Script line {}: {}", input.line, message);
+    let args = &input.args;
+    let arg = |index: usize| args.get(index)
+        .map(|arg| arg.as_str() )
+        .ok_or_else(|| error(format!("missing argument {} of {:?}", index + 1, input.command)) );
+    let number = |index: usize| arg(index).and_then(|value| parse::<f32>(value).map_err(error) );
+    let pressed = |index: usize| arg(index).and_then(|value| match value {
+        "pressed" => Ok(true),
+        "released" => Ok(false),
+        other => Err(error(format!("expected pressed or released, got {:?}", other))),
+    });
+
+    match input.command.as_str() {
+        "mouse_position" => client.update_mouse_position(number(0)?, number(1)?),
+        "mouse_inside" => client.update_mouse_inside(arg(0)? == "true"),
+        "mouse_button" => client.update_mouse_buttons(number(0)? as u8, pressed(1)?),
+        "wheel" => client.update_mouse_wheel(number(0)?, number(1)?),
+        "key" => client.update_keys(arg(0)?, pressed(1)?, false),
+        "modifiers" => {
+            let has = |name: &str| args.iter().any(|arg| arg == name );
+            client.update_modifiers(has("alt"), has("ctrl"), has("shift"), has("meta"));
+        },
+        "text" => client.update_text(&args.join(" ")),
+        "resize" => client.resize(number(0)? as u32, number(1)? as u32, number(2).unwrap_or(1.0)),
+        "touch" => {
+            let (id, x, y) = (number(1)? as u32, number(2)?, number(3)?);
+            match arg(0)? {
+                "start" => client.touch_start(id, x, y),
+                "move" => client.touch_move(id, x, y),
+                "end" => client.touch_end(id, x, y),
+                "cancel" => client.touch_cancel(id, x, y),
+                other => { return Err(error(format!("unknown touch phase {:?}", other))); }
+            }
+        },
+        other => { return Err(error(format!("unknown command {:?}", other))); }
+    }
+
+    Ok(())
+}
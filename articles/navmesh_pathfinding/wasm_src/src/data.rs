@@ -17,16 +17,48 @@ use gui::Gui;
 pub mod debug;
 use debug::DebugState;
 
+pub mod rng;
+use rng::Rng;
+
+pub mod touch;
+use touch::{TouchState, TouchPhase};
+
+pub mod actions;
+use actions::{ActionStates, Binding, KeyBindings};
+
+pub mod stats;
+use stats::Stats;
+
+pub mod strings;
+
 use crate::shared::{PositionF32, SizeF32, pos};
 use crate::store::StoreLoad;
 
 const ANIMATION_INTERVAL: f64 = 1000.0 / 16.0; // 16fps
 
+pub const MIN_VIEW_SCALE: f32 = 0.25;
+pub const MAX_VIEW_SCALE: f32 = 4.0;
+
+/// How far the view can go past the terrain edges, in world units
+const VIEW_MARGIN: f32 = 64.0;
+
+
+/// Clamps the device pixel ratio sent by the engine to a sane range
+pub fn sanitize_pixel_ratio(value: f32) -> f32 {
+    match value.is_finite() {
+        true => f32::clamp(value, 0.5, 4.0),
+        false => 1.0,
+    }
+}
 
-#[derive(Default, Copy, Clone)]
+#[derive(Copy, Clone, StoreLoad)]
+#[store(after_load = GlobalParams::after_load)]
 pub struct GlobalParams {
+    #[store(skip)]
     pub time: f64,
+    #[store(skip)]
     pub last_animation_tick: f64,
+    #[store(skip)]
     pub time_delta: f32,
 
     pub flags: base::GameFlags,
@@ -34,17 +66,33 @@ pub struct GlobalParams {
     
     pub mouse_position_old: PositionF32,
     pub mouse_position: PositionF32,
+    #[store(skip)]
+    pub mouse_inside: bool,
     pub view_offset: PositionF32,
+    #[store(skip)]
+    pub view_velocity: PositionF32,
+    pub view_scale: f32,
     pub view_size: SizeF32,
+    /// Physical pixels per css pixel. Mouse positions and `view_size` are in css pixels.
+    pub pixel_ratio: f32,
+    #[store(skip)]
     pub mouse_buttons: [base::ButtonState; 3],
+    #[store(skip)]
+    pub mouse_wheel_delta: f32,
+    #[store(skip)]
+    pub actions: ActionStates,
+    #[store(skip)]
+    pub modifiers: base::KeyModifiers,
+    #[store(skip)]
+    pub touches: TouchState,
+    /// Set when a touch tap emulated a primary mouse press that must be released on the next frame
+    #[store(skip)]
+    pub touch_release_primary: bool,
 
     pub total_sprites: u32,
 }
 
 impl GlobalParams {
-    pub fn primary_mouse_just_pressed(&self) -> bool { self.mouse_buttons[0].just_pressed() }
-    pub fn middle_mouse_just_pressed(&self) -> bool { self.mouse_buttons[2].just_pressed() }
-    pub fn middle_mouse_released(&self) -> bool { self.mouse_buttons[2].released() }
     pub fn mouse_moved(&self) -> bool {
         self.mouse_position_old.x != self.mouse_position.x || self.mouse_position_old.y != self.mouse_position.y
     }
@@ -57,17 +105,102 @@ impl GlobalParams {
             None
         }
     }
+
+    /// Converts a position in screen space (ex: the mouse position) into world space
+    pub fn screen_to_world(&self, position: PositionF32) -> PositionF32 {
+        (position / self.view_scale) - self.view_offset
+    }
+
+    pub fn mouse_world_position(&self) -> PositionF32 {
+        self.screen_to_world(self.mouse_position)
+    }
+
+    /// Scales the view by `factor` while keeping the world position under `anchor` (in screen space) in place
+    pub fn zoom_view(&mut self, factor: f32, anchor: PositionF32) {
+        let world_anchor = self.screen_to_world(anchor);
+        self.view_scale = f32::clamp(self.view_scale * factor, MIN_VIEW_SCALE, MAX_VIEW_SCALE);
+        self.view_offset = (anchor / self.view_scale) - world_anchor;
+        self.flags.set_update_view_offset();
+    }
+
+    /// Keeps the view over the terrain. `world` is the terrain size in world units and
+    /// `visible` is the size of the screen area where the world is rendered.
+    pub fn clamp_view(&mut self, world: SizeF32, visible: SizeF32) {
+        fn clamp_axis(offset: f32, world: f32, visible: f32) -> f32 {
+            if visible >= world + (VIEW_MARGIN * 2.0) {
+                (visible - world) * 0.5
+            } else {
+                f32::clamp(offset, visible - world - VIEW_MARGIN, VIEW_MARGIN)
+            }
+        }
+
+        let visible = pos(visible.width, visible.height) / self.view_scale;
+        let x = clamp_axis(self.view_offset.x, world.width, visible.x);
+        let y = clamp_axis(self.view_offset.y, world.height, visible.y);
+
+        if x != self.view_offset.x {
+            self.view_offset.x = x;
+            self.view_velocity.x = 0.0;
+            self.flags.set_update_view_offset();
+        }
+
+        if y != self.view_offset.y {
+            self.view_offset.y = y;
+            self.view_velocity.y = 0.0;
+            self.flags.set_update_view_offset();
+        }
+    }
     
 }
 
-#[derive(Default)]
+impl Default for GlobalParams {
+    fn default() -> Self {
+        GlobalParams {
+            time: 0.0,
+            last_animation_tick: 0.0,
+            time_delta: 0.0,
+            flags: base::GameFlags::default(),
+            debug_flags: base::DebugFlags::default(),
+            mouse_position_old: PositionF32::default(),
+            mouse_position: PositionF32::default(),
+            mouse_inside: false,
+            view_offset: PositionF32::default(),
+            view_velocity: PositionF32::default(),
+            view_scale: 1.0,
+            view_size: SizeF32::default(),
+            pixel_ratio: 1.0,
+            mouse_buttons: Default::default(),
+            mouse_wheel_delta: 0.0,
+            actions: ActionStates::default(),
+            modifiers: base::KeyModifiers::default(),
+            touches: TouchState::default(),
+            touch_release_primary: false,
+            total_sprites: 0,
+        }
+    }
+}
+
+#[derive(Default, StoreLoad)]
 pub struct GameData {
+    #[store(nested)]
     pub globals: GlobalParams,
+    /// Resolved again from `GameClientInit` when the client is loaded
+    #[store(skip)]
     pub assets: Assets,
+    #[store(nested)]
     pub world: World,
+    #[store(nested)]
     pub terrain: Terrain,
+    #[store(skip)]
     pub debug: DebugState,
+    #[store(nested)]
     pub gui: Gui,
+    #[store(nested)]
+    pub rng: Rng,
+    #[store(nested)]
+    pub bindings: KeyBindings,
+    #[store(skip)]
+    pub stats: Stats,
 }
 
 impl GameData {
@@ -104,6 +237,14 @@ impl GameData {
             global.mouse_position_old = global.mouse_position;
         }
 
+        // One finger taps act as a primary mouse click at the tap position
+        if let Some(tap) = global.touches.take_tap() {
+            global.mouse_position = tap;
+            global.mouse_buttons[0] = base::ButtonState::JustPressed;
+            global.touch_release_primary = true;
+            update_actions(&mut global.actions, &self.bindings, Binding::Mouse(0), true);
+        }
+
         // Note: Sprite animation are computed at sprite generation in `output.render_sprites` 
         let delta = new_time - global.last_animation_tick;
         if delta > ANIMATION_INTERVAL {
@@ -119,7 +260,20 @@ impl GameData {
         g.mouse_buttons[0].flip();
         g.mouse_buttons[1].flip();
         g.mouse_buttons[2].flip();
+        g.actions.flip();
         g.mouse_position_old = g.mouse_position;
+        g.mouse_wheel_delta = 0.0;
+        g.touches.finalize();
+
+        if ::std::mem::take(&mut g.touch_release_primary) {
+            g.mouse_buttons[0] = base::ButtonState::JustReleased;
+            update_actions(&mut g.actions, &self.bindings, Binding::Mouse(0), false);
+        }
+
+        let inspected = self.world.selected_sprites().first()
+            .and_then(|&entity| self.world.entity_snapshot(entity) );
+        self.gui.set_inspected(inspected);
+        self.gui.set_stats(&self.stats);
 
         if self.gui.update() {
             g.flags.set_update_gui();
@@ -128,9 +282,14 @@ impl GameData {
 
     pub fn update_mouse_position(&mut self, x: f32, y: f32) {
         self.globals.mouse_position = pos(x, y);
+        self.globals.mouse_inside = true;
         self.gui.update_mouse_position(x, y);
     }
 
+    pub fn update_mouse_inside(&mut self, inside: bool) {
+        self.globals.mouse_inside = inside;
+    }
+
     pub fn update_mouse_buttons(&mut self, button: u8, pressed: bool) {
         let index = button as usize;
         if index < self.globals.mouse_buttons.len() {
@@ -140,9 +299,83 @@ impl GameData {
             };
         }
 
+        // Mouse buttons pressed while the gui is waiting for a new binding are not game inputs
+        if !pressed || !self.gui.capturing_binding() {
+            update_actions(&mut self.globals.actions, &self.bindings, Binding::Mouse(button), pressed);
+        }
+
         self.gui.update_mouse_buttons(self.globals.mouse_position, button, pressed);
     }
 
+    pub fn update_mouse_wheel(&mut self, delta_x: f32, delta_y: f32) {
+        self.globals.mouse_wheel_delta += delta_y;
+        self.gui.update_mouse_wheel(self.globals.mouse_position, delta_x, delta_y);
+    }
+
+    pub fn update_touch(&mut self, id: u32, phase: TouchPhase, x: f32, y: f32) {
+        let position = pos(x, y);
+        let touches = &mut self.globals.touches;
+        match phase {
+            TouchPhase::Start => touches.start(id, position, self.globals.time),
+            TouchPhase::Move => touches.moved(id, position),
+            TouchPhase::End => touches.end(id, position, self.globals.time),
+            TouchPhase::Cancel => touches.cancel(id),
+        }
+
+        // A single finger also acts as the mouse cursor (ex: to preview sprites placement)
+        if touches.active_count() == 1 && phase != TouchPhase::Cancel {
+            self.globals.mouse_position = position;
+        }
+
+        self.gui.update_touch(id, phase, position);
+    }
+
+    pub fn update_keys(&mut self, key_name: &str, pressed: bool, repeat: bool) {
+        // Keys typed in a gui text field must not trigger actions. Releasing a key is always accepted.
+        let accepted = !pressed || !self.gui.wants_keyboard_input();
+        if accepted && !repeat {
+            if let Some(binding) = Binding::key(key_name) {
+                update_actions(&mut self.globals.actions, &self.bindings, binding, pressed);
+            }
+        }
+
+        self.gui.update_keys(key_name, pressed, repeat);
+    }
+
+    pub fn update_modifiers(&mut self, modifiers: base::KeyModifiers) {
+        self.globals.modifiers = modifiers;
+        self.gui.update_modifiers(modifiers);
+    }
+
+    pub fn update_text(&mut self, text: &str) {
+        self.gui.update_text(text);
+    }
+
+    pub fn update_composition(&mut self, text: &str, done: bool) {
+        self.gui.update_composition(text, done);
+    }
+
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+
+        // Held inputs may not map to the same actions anymore
+        self.globals.actions = ActionStates::default();
+        self.gui.set_bindings(bindings);
+    }
+
+    pub fn duplicate_sprite(&mut self, entity: hecs::Entity) {
+        const DUPLICATE_OFFSET: PositionF32 = PositionF32 { x: 32.0, y: 32.0 };
+        if self.world.duplicate_sprite(entity, DUPLICATE_OFFSET).is_some() {
+            self.globals.total_sprites += 1;
+        }
+    }
+
+    pub fn update_move_orders(&mut self) {
+        let seconds = self.globals.time_delta / 1000.0;
+        let atlas = &self.assets.atlas;
+        self.world.update_move_orders(seconds, atlas.pawn_idle, atlas.pawn_walk);
+    }
+
     pub fn add_pawn(&mut self, position: PositionF32) {
         let idle = self.assets.atlas.pawn_idle;
         self.world.add_pawn(position, idle.animate());
@@ -163,56 +396,13 @@ impl GameData {
 
 }
 
-impl StoreLoad for GameData {
-    fn store(&mut self, writer: &mut crate::store::StoreWriter) {
-        self.globals.store(writer);
-        self.assets.store(writer);
-        self.world.store(writer);
-        self.terrain.store(writer);
-        self.gui.store(writer);
-    }
-
-    fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
-        let mut data = GameData::default();
-        data.globals = GlobalParams::load(reader)?;
-        data.assets = Assets::load(reader)?;
-        data.world = World::load(reader)?;
-        data.terrain = Terrain::load(reader)?;
-        data.gui = Gui::load(reader)?;
-
-        data.gui.load_font(&data.assets)?;
-        data.gui.load_style();
-
-        Ok(data)
-    }
+fn update_actions(actions: &mut ActionStates, bindings: &KeyBindings, binding: Binding, pressed: bool) {
+    bindings.actions_for(binding, |action| actions.update(action, pressed) );
 }
 
-impl StoreLoad for GlobalParams {
-    fn store(&mut self, writer: &mut crate::store::StoreWriter) {
-        writer.write(&self.flags);
-        writer.write(&self.debug_flags);
-
-        writer.write(&self.mouse_position_old);
-        writer.write(&self.mouse_position);
-        writer.write(&self.view_offset);
-        writer.write(&self.view_size);
-        
-        writer.write(&self.total_sprites);
-        
-    }
-    
-    fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
-        let mut params = GlobalParams::default();
-        params.flags = reader.try_read()?;
-        params.debug_flags = reader.try_read()?;
-        
-        params.mouse_position_old = reader.try_read()?;
-        params.mouse_position = reader.try_read()?;
-        params.view_offset = reader.try_read()?;
-        params.view_size = reader.try_read()?;
-
-        params.total_sprites = reader.try_read()?;
-       
-        Ok(params)
+impl GlobalParams {
+    fn after_load(&mut self) -> Result<(), crate::error::Error> {
+        self.pixel_ratio = sanitize_pixel_ratio(self.pixel_ratio);
+        Ok(())
     }
 }
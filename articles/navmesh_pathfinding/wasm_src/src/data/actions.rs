@@ -0,0 +1,278 @@
+//! Maps keys and mouse buttons to named game actions.
+//! The default bindings are loaded from the `key_bindings` csv in the assets bundle. Each line is
+//! `action;binding;binding;` where a binding is either a `KeyboardEvent.code` value or `MouseN`.
+use super::base::ButtonState;
+use crate::store::StoreLoad;
+
+pub const ACTION_COUNT: usize = 10;
+pub const BINDINGS_PER_ACTION: usize = 2;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    Pan,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    Select,
+    Command,
+    Delete,
+    PlaceHouse,
+    CycleState,
+}
+
+impl Action {
+    pub const ALL: [Action; ACTION_COUNT] = [
+        Action::Pan,
+        Action::PanUp,
+        Action::PanDown,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::Select,
+        Action::Command,
+        Action::Delete,
+        Action::PlaceHouse,
+        Action::CycleState,
+    ];
+
+    /// Name of the action in the bindings csv
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Pan => "pan",
+            Action::PanUp => "pan_up",
+            Action::PanDown => "pan_down",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::Select => "select",
+            Action::Command => "command",
+            Action::Delete => "delete",
+            Action::PlaceHouse => "place_house",
+            Action::CycleState => "cycle_state",
+        }
+    }
+
+    /// Name of the action in the gui
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Pan => "Pan (drag)",
+            Action::PanUp => "Pan up",
+            Action::PanDown => "Pan down",
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::Select => "Select / Use tool",
+            Action::Command => "Cancel / Move / Menu",
+            Action::Delete => "Delete selection",
+            Action::PlaceHouse => "Place house",
+            Action::CycleState => "Next demo",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name )
+    }
+}
+
+/// `KeyboardEvent.code` values that can be bound to an action
+const KEY_NAMES: &[&str] = &[
+    "KeyA", "KeyB", "KeyC", "KeyD", "KeyE", "KeyF", "KeyG", "KeyH", "KeyI", "KeyJ", "KeyK", "KeyL", "KeyM",
+    "KeyN", "KeyO", "KeyP", "KeyQ", "KeyR", "KeyS", "KeyT", "KeyU", "KeyV", "KeyW", "KeyX", "KeyY", "KeyZ",
+    "Digit0", "Digit1", "Digit2", "Digit3", "Digit4", "Digit5", "Digit6", "Digit7", "Digit8", "Digit9",
+    "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12",
+    "ArrowUp", "ArrowDown", "ArrowLeft", "ArrowRight",
+    "Space", "Enter", "Escape", "Tab", "Backspace", "Delete", "Insert", "Home", "End", "PageUp", "PageDown",
+    "ShiftLeft", "ShiftRight", "ControlLeft", "ControlRight", "AltLeft", "AltRight",
+    "Minus", "Equal", "BracketLeft", "BracketRight", "Semicolon", "Quote", "Backquote", "Backslash", "Comma", "Period", "Slash",
+    "Numpad0", "Numpad1", "Numpad2", "Numpad3", "Numpad4", "Numpad5", "Numpad6", "Numpad7", "Numpad8", "Numpad9",
+];
+
+/// Mouse buttons, in the same order as `GlobalParams::mouse_buttons`
+const MOUSE_NAMES: &[&str] = &["Mouse0", "Mouse1", "Mouse2"];
+const MOUSE_LABELS: &[&str] = &["Left mouse", "Right mouse", "Middle mouse"];
+
+#[derive(Default, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Binding {
+    #[default]
+    None,
+    /// Index in `KEY_NAMES`
+    Key(u16),
+    Mouse(u8),
+}
+
+impl Binding {
+
+    pub fn key(key_name: &str) -> Option<Binding> {
+        KEY_NAMES.iter()
+            .position(|&name| name == key_name )
+            .map(|index| Binding::Key(index as u16) )
+    }
+
+    pub fn from_name(name: &str) -> Option<Binding> {
+        match MOUSE_NAMES.iter().position(|&mouse| mouse == name ) {
+            Some(button) => Some(Binding::Mouse(button as u8)),
+            None => Binding::key(name)
+        }
+    }
+
+    /// Name of the binding in the bindings csv
+    pub fn name(self) -> &'static str {
+        match self {
+            Binding::None => "",
+            Binding::Key(index) => KEY_NAMES.get(index as usize).copied().unwrap_or(""),
+            Binding::Mouse(button) => MOUSE_NAMES.get(button as usize).copied().unwrap_or(""),
+        }
+    }
+
+    /// Name of the binding in the gui
+    pub fn label(self) -> &'static str {
+        match self {
+            Binding::None => "-",
+            Binding::Key(_) => self.name(),
+            Binding::Mouse(button) => MOUSE_LABELS.get(button as usize).copied().unwrap_or("-"),
+        }
+    }
+
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: [[Binding; BINDINGS_PER_ACTION]; ACTION_COUNT],
+}
+
+impl KeyBindings {
+
+    pub fn load_csv(&mut self, csv: &str) {
+        crate::shared::split_csv::<{BINDINGS_PER_ACTION + 2}, _>(csv, |args| {
+            let Some(action) = Action::from_name(args[0]) else {
+                warn!("Unknown action {:?}", args[0]);
+                return;
+            };
+
+            let mut bindings = [Binding::None; BINDINGS_PER_ACTION];
+            for (slot, &name) in args[1..].iter().filter(|name| !name.trim().is_empty() ).take(BINDINGS_PER_ACTION).enumerate() {
+                match Binding::from_name(name.trim()) {
+                    Some(binding) => { bindings[slot] = binding; },
+                    None => { warn!("Unknown binding {:?} for action {:?}", name, args[0]); }
+                }
+            }
+
+            self.bindings[action as usize] = bindings;
+        });
+    }
+
+    pub fn to_csv(self) -> String {
+        let mut csv = String::with_capacity(ACTION_COUNT * 32);
+        for action in Action::ALL {
+            csv.push_str(action.name());
+            csv.push(';');
+            for binding in self.get(action) {
+                csv.push_str(binding.name());
+                csv.push(';');
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn get(&self, action: Action) -> [Binding; BINDINGS_PER_ACTION] {
+        self.bindings[action as usize]
+    }
+
+    pub fn set(&mut self, action: Action, slot: usize, binding: Binding) {
+        if let Some(value) = self.bindings[action as usize].get_mut(slot) {
+            *value = binding;
+        }
+    }
+
+    /// Calls `callback` for every action bound to `binding`
+    pub fn actions_for<CB: FnMut(Action)>(&self, binding: Binding, mut callback: CB) {
+        if binding == Binding::None {
+            return;
+        }
+
+        for action in Action::ALL {
+            if self.get(action).contains(&binding) {
+                callback(action);
+            }
+        }
+    }
+
+}
+
+/// The `key_bindings` csv of the assets bundle
+const DEFAULT_BINDINGS_CSV: &str = include_str!("../../../assets/key_bindings.csv");
+
+impl Default for KeyBindings {
+    /// Used if the assets bundle doesn't define the bindings
+    fn default() -> Self {
+        let mut bindings = KeyBindings { bindings: [[Binding::None; BINDINGS_PER_ACTION]; ACTION_COUNT] };
+        bindings.load_csv(DEFAULT_BINDINGS_CSV);
+        bindings
+    }
+}
+
+const CSV_TAG: u32 = crate::store::tag("csv");
+
+impl StoreLoad for KeyBindings {
+    fn store(&mut self, writer: &mut crate::store::StoreWriter) {
+        writer.write_section("csv", |writer| writer.write_str(&self.to_csv()) );
+    }
+
+    fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
+        let mut bindings = KeyBindings::default();
+        reader.read_sections(|reader, tag| {
+            match tag {
+                CSV_TAG => bindings.load_csv(reader.read_str()?),
+                _ => { return Ok(false); }
+            }
+            Ok(true)
+        })?;
+        Ok(bindings)
+    }
+
+    fn schema(schema: &mut crate::store::Schema) {
+        schema.string("csv");
+    }
+}
+
+/// The state of every action for the current frame
+#[derive(Default, Copy, Clone)]
+pub struct ActionStates {
+    states: [ButtonState; ACTION_COUNT],
+    /// Number of bindings currently held for each action
+    held: [u8; ACTION_COUNT],
+}
+
+impl ActionStates {
+
+    pub fn update(&mut self, action: Action, pressed: bool) {
+        let index = action as usize;
+        let held = &mut self.held[index];
+        if pressed {
+            *held = held.saturating_add(1);
+            if *held == 1 {
+                self.states[index] = ButtonState::JustPressed;
+            }
+        } else if *held > 0 {
+            *held -= 1;
+            if *held == 0 {
+                self.states[index] = ButtonState::JustReleased;
+            }
+        }
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool { self.states[action as usize].just_pressed() }
+    pub fn pressed(&self, action: Action) -> bool { !self.states[action as usize].released() }
+    pub fn released(&self, action: Action) -> bool { self.states[action as usize].released() }
+
+    pub fn any_pan(&self) -> bool {
+        self.pressed(Action::PanUp) || self.pressed(Action::PanDown) || self.pressed(Action::PanLeft) || self.pressed(Action::PanRight)
+    }
+
+    /// Called at the end of a frame
+    pub fn flip(&mut self) {
+        for state in self.states.iter_mut() {
+            state.flip();
+        }
+    }
+
+}
@@ -2,14 +2,18 @@ use fnv::FnvHashMap;
 use zerocopy_derive::{Immutable, IntoBytes, FromBytes};
 use crate::error::Error;
 use crate::shared::AABB;
-use crate::store::StoreLoad;
 use crate::GameClientInit;
 use super::base::{AnimatedSprite, StaticSprite};
+use super::actions::KeyBindings;
+use super::strings::Strings;
 
-#[derive(Copy, Clone, FromBytes, IntoBytes, Immutable)]
+#[derive(Copy, Clone, Default, FromBytes, IntoBytes, Immutable)]
 pub struct Texture {
     // The unique ID of the texture that identify the resource on the engine side
     pub id: u32,
+    // Size of the texture in pixels. Zero if the engine didn't send it.
+    pub width: u32,
+    pub height: u32,
 }
 
 #[derive(Copy, Clone, Default, FromBytes, IntoBytes, Immutable)]
@@ -45,10 +49,15 @@ impl AtlasData {
     }
 }
 
+#[derive(Default)]
 pub struct Assets {
     pub textures: FnvHashMap<String, Texture>,
     pub fonts: FnvHashMap<String, Vec<u8>>,
-    pub atlas: AtlasData
+    pub atlas: AtlasData,
+    /// Default key bindings. The bindings edited by the user are in `GameData::bindings`
+    pub key_bindings: KeyBindings,
+    /// Gui strings of the language selected in `GameClientInit`
+    pub strings: Strings,
 }
 
 impl Assets {
@@ -56,11 +65,18 @@ impl Assets {
     pub fn init(&mut self, init: &GameClientInit) -> Result<(), Error> {
         self.import_assets_index(init)?;
 
+        for (name, texture) in self.textures.iter_mut() {
+            if let Some(&[width, height]) = init.texture_sizes.get(name) {
+                texture.width = width;
+                texture.height = height;
+            }
+        }
+
         self.atlas.texture = self.textures.get("atlas")
             .copied()
             .ok_or_else(|| assets_err!("Missing texture \"atlas\" ") )?;
 
-        self.textures.insert("TEST".to_string(), Texture { id: 999 });
+        self.textures.insert("TEST".to_string(), Texture { id: 999, ..Texture::default() });
 
         Ok(())
     }
@@ -71,7 +87,7 @@ impl Assets {
             .ok_or_else(|| assets_err!("Missing texture name") )?;
 
         let id = self.textures.len() as u32;
-        self.textures.insert(name, Texture { id });
+        self.textures.insert(name, Texture { id, ..Texture::default() });
 
         Ok(())
     }
@@ -86,6 +102,7 @@ impl Assets {
         // Each CSV had its own loading procedure
         match csv_name {
             "atlas_sprites" => self.atlas.load_csv(csv_string),
+            "key_bindings" => self.key_bindings.load_csv(csv_string),
             name => {
                 warn!("Unknown csv: {:?}", name);
             }
@@ -94,6 +111,24 @@ impl Assets {
         Ok(())
     }
 
+    fn load_strings(&mut self, init: &GameClientInit, args: &[&str]) -> Result<(), Error> {
+        let &language = args.get(1)
+            .ok_or_else(|| assets_err!("Missing strings language") )?;
+
+        // Only the selected language is loaded
+        if language != init.language {
+            return Ok(());
+        }
+
+        // The engine uploads string tables as text assets named `strings_[language]`
+        let csv_string = init.text_assets.get(&format!("strings_{language}"))
+            .ok_or_else(|| assets_err!("Failed to match strings language {:?} to strings data", language) )?;
+
+        self.strings.load_csv(language, csv_string);
+
+        Ok(())
+    }
+
     fn load_font(&mut self, init: &GameClientInit, args: &[&str]) -> Result<(), Error> {
         let &font_name = args.get(1)
             .ok_or_else(|| assets_err!("Missing font name") )?;
@@ -121,6 +156,9 @@ impl Assets {
                 "FONT" => {
                     self.load_font(init, args)
                 }
+                "STRINGS" => {
+                    self.load_strings(init, args)
+                }
                 "SHADER" => Ok(()),
                 _ => { Err(assets_err!("Unknown asset type {:?}", args[0])) }
             };
@@ -139,34 +177,3 @@ impl Assets {
 
 }
 
-impl StoreLoad for Assets {
-    fn store(&mut self, writer: &mut crate::store::StoreWriter) {
-        writer.write_string_hashmap(&self.textures);
-        writer.write_string_array_hashmap(&self.fonts);
-        writer.write(&self.atlas);
-    }
-
-    fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
-        let mut data = Assets::default();
-        data.textures = reader.read_string_hashmap();
-        data.fonts = reader.read_string_array_hashmap();
-        data.atlas = reader.try_read()?;
-        Ok(data)
-    }
-}
-
-impl Default for Texture {
-    fn default() -> Self {
-        Texture { id: 0 }
-    }
-}
-
-impl Default for Assets {
-    fn default() -> Self {
-        Assets {
-            textures: FnvHashMap::default(),
-            fonts: FnvHashMap::default(),
-            atlas: AtlasData::default(),
-        }
-    }
-}
@@ -34,15 +34,44 @@ impl DebugFlags {
     pub const SHOW_CELL_CENTERS: u32 = 0x8;
     pub const SHOW_PATH: u32 = 0x10;
     pub const SHOW_BLOCKED_CELLS: u32 = 0x20;
+    pub const SHOW_PERFORMANCE: u32 = 0x40;
+
+    #[inline(always)] pub const fn show_performance(&self) -> bool { self.0 & Self::SHOW_PERFORMANCE > 0 }
 }
 
-#[derive(Default, Copy, Clone, FromBytes, IntoBytes, Immutable)]
+/// State of the keyboard modifiers keys
+#[derive(Default, Copy, Clone, PartialEq, FromBytes, IntoBytes, Immutable)]
+pub struct KeyModifiers(pub u32);
+
+impl KeyModifiers {
+    pub const ALT: u32 = 0b0001;
+    pub const CTRL: u32 = 0b0010;
+    pub const SHIFT: u32 = 0b0100;
+    pub const META: u32 = 0b1000;
+
+    pub fn new(alt: bool, ctrl: bool, shift: bool, meta: bool) -> Self {
+        let mut value = 0;
+        if alt { value |= Self::ALT; }
+        if ctrl { value |= Self::CTRL; }
+        if shift { value |= Self::SHIFT; }
+        if meta { value |= Self::META; }
+        KeyModifiers(value)
+    }
+
+    #[inline(always)] pub const fn alt(&self) -> bool { self.0 & Self::ALT > 0 }
+    #[inline(always)] pub const fn ctrl(&self) -> bool { self.0 & Self::CTRL > 0 }
+    #[inline(always)] pub const fn shift(&self) -> bool { self.0 & Self::SHIFT > 0 }
+    #[inline(always)] pub const fn meta(&self) -> bool { self.0 & Self::META > 0 }
+}
+
+#[derive(Default, Copy, Clone, PartialEq, FromBytes, IntoBytes, Immutable)]
 pub struct BaseSpriteFlags(pub u8);
 
 impl BaseSpriteFlags {
     pub const FLIPPED: u8  = 0x1;
     pub const HIGHLIGHTED: u8 = 0x2;
 
+    flags!(flipped, set_flipped, clear_flipped, Self::FLIPPED);
     flags!(highlighted, set_highlighted, clear_highlighted, Self::HIGHLIGHTED);
 
     #[inline(always)]
@@ -51,7 +80,7 @@ impl BaseSpriteFlags {
     }
 }
 
-#[derive(Copy, Clone, FromBytes, IntoBytes, Immutable)]
+#[derive(Copy, Clone, PartialEq, FromBytes, IntoBytes, Immutable)]
 pub struct BaseSprite {
     pub position: PositionF32,
     pub texcoord: AABB,
@@ -65,7 +94,7 @@ impl BaseSprite {
     }
 }
 
-#[derive(Default, Copy, Clone, FromBytes, IntoBytes, Immutable)]
+#[derive(Default, Copy, Clone, PartialEq, FromBytes, IntoBytes, Immutable)]
 pub struct AnimationState {
     pub x: f32,
     pub y: f32,
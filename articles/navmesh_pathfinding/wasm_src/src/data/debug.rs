@@ -4,6 +4,7 @@ use crate::shared::AABB;
 
 #[derive(Copy, Clone, Immutable, IntoBytes, TryFromBytes)]
 #[repr(C)]
+#[allow(dead_code)]
 pub enum DebugElement {
     Rect { base: AABB, line_thickness: f32, color: [u8; 4] },
 }
@@ -16,13 +17,14 @@ pub struct DebugState {
 impl DebugState {
 
     pub fn any(&self) -> bool {
-        self.elements.len() > 0
+        !self.elements.is_empty()
     }
 
     pub fn clear(&mut self) {
         self.elements.clear();
     }
 
+    #[allow(dead_code)]
     pub fn draw_rect(&mut self, rect: AABB, line_thickness: f32, color: [u8; 4]) {
         self.elements.push(DebugElement::Rect { base: rect, line_thickness, color });
     }
@@ -78,6 +80,7 @@ struct GenerateMeshState<'a> {
 
 impl<'a> GenerateMeshState<'a> {
 
+    #[allow(clippy::identity_op)]
     fn generate_rect(&mut self, element: DebugElement) {
         let (base, t, color) = match element {
             DebugElement::Rect { base, line_thickness, color } => (base, line_thickness, color)
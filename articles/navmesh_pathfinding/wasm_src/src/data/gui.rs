@@ -2,33 +2,157 @@
 mod components;
 
 use crate::shared::PositionF32;
-use crate::store::StoreLoad;
-use crate::data::base::DebugFlags;
+use crate::data::base::{DebugFlags, KeyModifiers};
+use crate::data::actions::{Action, Binding, KeyBindings};
+use crate::data::world::EntitySnapshot;
+use crate::data::stats::Stats;
+use crate::data::strings::Strings;
+use crate::data::touch::TouchPhase;
 use crate::state::{GameStateValue, GameInputType};
 use crate::GameClientInit;
+use crate::store::StoreLoad;
 
 #[derive(Copy, Clone)]
 pub enum GuiEvent {
     GameStateValueChanged(GameStateValue),
     SetDebugFlags(DebugFlags),
     SetInputType(GameInputType),
+    SetFollowSelection(bool),
+    SetBinding(Action, usize, Binding),
+    InspectEntity(hecs::Entity),
+    DuplicateEntity(hecs::Entity),
+    DeleteEntity(hecs::Entity),
+    EditEntity(EntitySnapshot),
+    ResetBindings,
     ResetWorld,
     ResetPawnPosition,
 }
 
+/// Actions menu of a world entity, opened with a right click
+#[derive(Copy, Clone)]
+struct ContextMenu {
+    entity: hecs::Entity,
+    position: egui::Pos2,
+}
+
+/// A sprite of the atlas displayed as an egui image
+#[derive(Copy, Clone)]
+pub(crate) struct GuiIcon {
+    pub texture: egui::TextureId,
+    /// Normalized texture coordinates of the sprite
+    pub uv: egui::Rect,
+    pub size: egui::Vec2,
+}
+
+/// Icons of the game tools. `None` if the engine didn't send the size of the atlas.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct GuiIcons {
+    pub pawn: Option<GuiIcon>,
+    pub castle: Option<GuiIcon>,
+    pub house: Option<GuiIcon>,
+}
+
+impl GuiIcons {
+
+    fn from_atlas(atlas: &crate::data::assets::AtlasData) -> GuiIcons {
+        let texture = atlas.texture;
+        if texture.width == 0 || texture.height == 0 {
+            return GuiIcons::default();
+        }
+
+        // Atlas textures are user textures, the engine resolves them using the asset texture id
+        let texture_id = egui::TextureId::User(texture.id as u64);
+        let texture_size = egui::vec2(texture.width as f32, texture.height as f32);
+        let icon = |sprite: crate::data::base::StaticSprite| {
+            let coords = sprite.texcoord;
+            let min = egui::pos2(coords.left, coords.top);
+            let max = egui::pos2(coords.right, coords.bottom);
+            Some(GuiIcon {
+                texture: texture_id,
+                uv: egui::Rect::from_min_max((min.to_vec2() / texture_size).to_pos2(), (max.to_vec2() / texture_size).to_pos2()),
+                size: max - min,
+            })
+        };
+
+        GuiIcons {
+            pawn: icon(atlas.pawn_idle.sprite()),
+            castle: icon(atlas.castle),
+            house: icon(atlas.house),
+        }
+    }
+
+}
+
+/// Height of the bottom panel when it's collapsed
+const COLLAPSED_HEIGHT: f32 = 32.0;
+const MIN_PANEL_HEIGHT: f32 = 120.0;
+
 /// Egui wrapper
+#[derive(StoreLoad)]
+#[store(after_load = Gui::after_load)]
 pub struct Gui {
+    #[store(skip)]
     ctx: egui::Context,
+    #[store(skip)]
     input: Box<egui::RawInput>,
+    #[store(skip)]
     output: Box<egui::FullOutput>,
+    /// Height of the bottom panel when it's expanded
     height: f32,
+    collapsed: bool,
+    /// Screen area of the bottom panel on the last frame
+    #[store(skip)]
+    panel_rect: egui::Rect,
+    /// True if the last mouse position was sent to egui
+    #[store(skip)]
+    pointer_inside: bool,
     pixel_per_point: f32,
     max_texture_size: u32,
     view: [f32; 4],
+    #[store(skip)]
     game_state: GameStateValue,
+    #[store(skip)]
     game_input: GameInputType,
+    #[store(skip)]
     debug_flags: DebugFlags,
+    #[store(skip)]
+    follow_selection: bool,
+    #[store(skip)]
+    bindings: KeyBindings,
+    #[store(skip)]
+    icons: GuiIcons,
+    #[store(skip)]
+    strings: Strings,
+    #[store(skip)]
+    show_settings: bool,
+    /// The action binding waiting for a key or a mouse button
+    #[store(skip)]
+    rebinding: Option<(Action, usize)>,
+    #[store(skip)]
+    context_menu: Option<ContextMenu>,
+    /// The selected entity shown in the inspector
+    #[store(skip)]
+    inspected: Option<EntitySnapshot>,
+    /// Copy of the performance stats. Only updated when the performance overlay is visible.
+    #[store(skip)]
+    stats: Box<Stats>,
+    /// The touch emulating the egui pointer
+    #[store(skip)]
+    pointer_touch: Option<u32>,
+    #[store(skip)]
+    modifiers: egui::Modifiers,
+    /// True while an IME composition is in progress
+    #[store(skip)]
+    composing: bool,
+    #[store(skip)]
     events: Vec<GuiEvent>,
+    /// Shapes of the last tessellated frame
+    #[store(skip)]
+    last_shapes: Vec<egui::epaint::ClippedShape>,
+    /// Tessellate the next frame even if the shapes didn't change
+    #[store(skip)]
+    retessellate: bool,
+    #[store(skip)]
     force_repaint: bool,
 }
 
@@ -37,8 +161,9 @@ impl Gui {
     pub fn init(&mut self, init: &GameClientInit, assets: &crate::data::Assets) -> Result<(), crate::Error> {
         let input = &mut self.input;
 
-        let min = egui::Pos2 { x: 0.0, y: init.view_size.height - self.height };
-        let size = egui::Vec2 { x: init.view_size.width, y: self.height };
+        // Egui covers the whole screen, the panels only use the bottom part
+        let min = egui::Pos2::ZERO;
+        let size = egui::Vec2 { x: init.view_size.width, y: init.view_size.height };
         input.screen_rect = Some(egui::Rect::from_min_size(min, size));
 
         input.max_texture_side = Some(init.max_texture_size as usize);
@@ -47,13 +172,22 @@ impl Gui {
 
         self.view = [min.x, min.y, size.x, size.y];
         self.max_texture_size = init.max_texture_size;
+        self.set_pixel_ratio(init.pixel_ratio);
 
         self.load_font(assets)?;
         self.load_style();
+        self.set_assets(assets);
 
         Ok(())
     }
 
+    /// Icons and strings are not saved with the gui, they must be set again after a reload
+    pub fn set_assets(&mut self, assets: &crate::data::Assets) {
+        self.icons = GuiIcons::from_atlas(&assets.atlas);
+        self.strings = assets.strings.clone();
+        self.force_repaint = true;
+    }
+
     // UI generation happens here
     pub fn update(&mut self) -> bool {
         let input = egui::RawInput::take(&mut self.input);
@@ -62,44 +196,124 @@ impl Gui {
         let width = self.view[2];
         let left_panel_width = 140.0;
 
-        egui::CentralPanel::default().show(&self.ctx, |ui| {
-            components::left_panel(ui, components::LeftPanelParams {
-                events: &mut self.events,
-                state: &mut self.game_state,
-                state_input: &mut self.game_input,
-                panel_width: left_panel_width,
-            });
+        if self.collapsed {
+            let panel = egui::TopBottomPanel::bottom("bottom_panel")
+                .resizable(false)
+                .exact_height(COLLAPSED_HEIGHT)
+                .show(&self.ctx, |ui| components::collapsed_panel(ui, &self.strings, &mut self.collapsed) );
+
+            self.panel_rect = panel.response.rect;
+        } else {
+            let max_height = f32::max(MIN_PANEL_HEIGHT, self.view[3] * 0.8);
+            let ctx = self.ctx.clone();
+            let panel = egui::TopBottomPanel::bottom("bottom_panel")
+                .resizable(true)
+                .default_height(self.height)
+                .height_range(MIN_PANEL_HEIGHT..=max_height)
+                .show(&ctx, |ui| self.bottom_panel(ui, width, left_panel_width) );
+
+            self.height = panel.response.rect.height();
+            self.panel_rect = panel.response.rect;
+        }
+
+        self.show_context_menu();
+
+        if self.debug_flags.show_performance() {
+            self.show_performance_overlay();
+        }
+
+        *self.output = self.ctx.end_pass();
+
+        self.ctx.has_requested_repaint() || ::std::mem::take(&mut self.force_repaint)
+    }
+
+    fn bottom_panel(&mut self, ui: &mut egui::Ui, width: f32, left_panel_width: f32) {
+        components::left_panel(ui, components::LeftPanelParams {
+            events: &mut self.events,
+            state: &mut self.game_state,
+            state_input: &mut self.game_input,
+            debug_flags: &mut self.debug_flags,
+            show_settings: &mut self.show_settings,
+            collapsed: &mut self.collapsed,
+            strings: &self.strings,
+            panel_width: left_panel_width,
+        });
 
-            let params = components::PanelParams {
+        if self.show_settings {
+            let params = components::SettingsPanelParams {
                 events: &mut self.events,
-                debug_flags: &mut self.debug_flags,
-                state_input: &mut self.game_input,
+                bindings: &self.bindings,
+                rebinding: &mut self.rebinding,
+                strings: &self.strings,
             };
 
-            components::right_panel(ui, width-left_panel_width, |ui| {
-                match self.game_state {
-                    GameStateValue::Generation => components::generation_panel(ui, params),
-                    GameStateValue::Navigation => components::navigation_panel(ui, params),
-                    GameStateValue::Obstacles => components::obstacles_panel(ui, params),
-                    GameStateValue::FinalDemo => components::final_panel(ui, params),
-                    _ => {}
-                }
-            })
+            components::right_panel(ui, width-left_panel_width, |ui| components::settings_panel(ui, params));
+            return;
+        }
+
+        let params = components::PanelParams {
+            events: &mut self.events,
+            debug_flags: &mut self.debug_flags,
+            state_input: &mut self.game_input,
+            follow_selection: &mut self.follow_selection,
+            icons: &self.icons,
+            strings: &self.strings,
+        };
+
+        components::right_panel(ui, width-left_panel_width, |ui| {
+            components::inspector_panel(ui, params.events, params.strings, &mut self.inspected);
+
+            match self.game_state {
+                GameStateValue::Generation => components::generation_panel(ui, params),
+                GameStateValue::Navigation => components::navigation_panel(ui, params),
+                GameStateValue::Obstacles => components::obstacles_panel(ui, params),
+                GameStateValue::FinalDemo => components::final_panel(ui, params),
+                _ => {}
+            }
         });
+    }
 
-        *self.output = self.ctx.end_pass();
+    fn show_context_menu(&mut self) {
+        let Some(menu) = self.context_menu else {
+            return;
+        };
 
-        self.ctx.has_requested_repaint() || ::std::mem::take(&mut self.force_repaint)
+        // The menu is drawn over the world, outside of the gui panel
+        let area = egui::Area::new(egui::Id::new("entity_context_menu"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(menu.position)
+            .show(&self.ctx, |ui| components::context_menu(ui, &mut self.events, &self.strings, menu.entity) );
+
+        if area.inner {
+            self.close_context_menu();
+        }
+    }
+
+    fn show_performance_overlay(&mut self) {
+        // Drawn in the top right corner of the world. The overlay doesn't capture any input.
+        egui::Area::new(egui::Id::new("performance_overlay"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::RIGHT_TOP, egui::Vec2 { x: -10.0, y: 10.0 })
+            .interactable(false)
+            .show(&self.ctx, |ui| components::performance_overlay(ui, &self.strings, &self.stats) );
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) {
-        let min = egui::Pos2 { x: 0.0, y: (height as f32) - self.height };
-        let size = egui::Vec2 { x: (width as f32), y: self.height };
+    /// `width` and `height` are in css pixels, which map 1:1 to egui points
+    pub fn resize(&mut self, width: u32, height: u32, pixel_ratio: f32) {
+        let min = egui::Pos2::ZERO;
+        let size = egui::Vec2 { x: (width as f32), y: (height as f32) };
         self.input.screen_rect = Some(egui::Rect::from_min_size(min, size));
         self.view = [min.x, min.y, size.x, size.y];
+        self.set_pixel_ratio(pixel_ratio);
         self.force_repaint = true;
     }
 
+    fn set_pixel_ratio(&mut self, pixel_ratio: f32) {
+        self.pixel_per_point = pixel_ratio;
+        self.retessellate = true;
+        self.input.viewports.entry(egui::ViewportId::ROOT).or_default().native_pixels_per_point = Some(pixel_ratio);
+    }
+
     pub fn set_state(&mut self, state: GameStateValue, input: GameInputType) {
         self.game_state = state;
         self.game_input = input;
@@ -111,17 +325,74 @@ impl Gui {
         self.force_repaint = true;
     }
 
+    /// The y coordinate of the top of the bottom panel, in screen space. Floating areas are drawn over the world and are not included.
+    pub fn top(&self) -> f32 {
+        match self.panel_rect.is_positive() {
+            true => self.panel_rect.top(),
+            false => self.view[3],
+        }
+    }
+
+    pub fn set_follow_selection(&mut self, follow: bool) {
+        self.follow_selection = follow;
+        self.force_repaint = true;
+    }
+
+    pub fn set_bindings(&mut self, bindings: KeyBindings) {
+        self.bindings = bindings;
+        self.force_repaint = true;
+    }
+
+    /// True if the gui is waiting for the user to press the key or the mouse button of a new binding
+    pub fn capturing_binding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+
+    pub fn set_inspected(&mut self, snapshot: Option<EntitySnapshot>) {
+        if self.inspected != snapshot {
+            self.inspected = snapshot;
+            self.force_repaint = true;
+        }
+    }
+
+    pub fn set_stats(&mut self, stats: &Stats) {
+        // The overlay is redrawn every frame while it's visible
+        if self.debug_flags.show_performance() {
+            *self.stats = *stats;
+            self.force_repaint = true;
+        }
+    }
+
+    pub fn open_context_menu(&mut self, entity: hecs::Entity, position: PositionF32) {
+        let position = egui::Pos2 { x: position.x, y: position.y };
+        self.context_menu = Some(ContextMenu { entity, position });
+        self.force_repaint = true;
+    }
+
+    pub fn close_context_menu(&mut self) {
+        if self.context_menu.take().is_some() {
+            self.force_repaint = true;
+        }
+    }
+
     pub fn position_outside_gui(&self, position: PositionF32) -> bool {
-        position.y < self.view[1]
+        let position = egui::Pos2 { x: position.x, y: position.y };
+
+        // Floating areas (ex: the context menu) are hit tested by egui. Areas that are not interactable, like the
+        // performance overlay, are ignored so the world under them can still be clicked.
+        let over_area = self.ctx.layer_id_at(position)
+            .map(|layer| layer.order != egui::Order::Background )
+            .unwrap_or(false);
+
+        !self.panel_rect.contains(position) && !over_area
     }
 
     pub fn events(&mut self) -> Vec<GuiEvent> {
-        let cloned;
-        if self.events.len() > 0 {
-            cloned = self.events.clone();
+        let cloned = if !self.events.is_empty() {
+            self.events.clone()
         } else {
-            cloned = Vec::new();
-        }
+            Vec::new()
+        };
         self.events.clear();
         cloned
     }
@@ -133,14 +404,27 @@ impl Gui {
     }
 
     pub fn update_mouse_position(&mut self, x: f32, y: f32) {
-        if y >= self.view[1] {
+        // Egui keeps receiving the mouse position while a widget is dragged (ex: resizing the panel)
+        let inside = !self.position_outside_gui(PositionF32 { x, y }) || self.ctx.is_using_pointer();
+        if inside {
             self.input.events.push(egui::Event::MouseMoved(egui::Vec2 { x, y }));
             self.input.focused = true;
+        } else if self.pointer_inside {
+            self.input.events.push(egui::Event::PointerGone);
         }
+
+        self.pointer_inside = inside;
     }
 
     pub fn update_mouse_buttons(&mut self, position: PositionF32, button: u8, pressed: bool) {
-        use egui::{Event, Modifiers, PointerButton};
+        use egui::{Event, PointerButton};
+
+        if pressed {
+            if let Some((action, slot)) = self.rebinding.take() {
+                self.events.push(GuiEvent::SetBinding(action, slot, Binding::Mouse(button)));
+                return;
+            }
+        }
         
         let pos = egui::Pos2 { x: position.x, y: position.y };
         let button = match button {
@@ -151,37 +435,178 @@ impl Gui {
         };
 
         if let Some(button) = button {
-            self.input.events.push(Event::PointerButton { pos, button, pressed, modifiers: Modifiers::default() });
+            self.input.events.push(Event::PointerButton { pos, button, pressed, modifiers: self.modifiers });
+        }
+    }
+
+    pub fn update_mouse_wheel(&mut self, position: PositionF32, delta_x: f32, delta_y: f32) {
+        use egui::{Event, MouseWheelUnit};
+
+        if !self.position_outside_gui(position) {
+            // Browsers report a positive delta when scrolling down, egui expects the opposite
+            let delta = egui::Vec2 { x: -delta_x, y: -delta_y };
+            self.input.events.push(Event::MouseWheel { unit: MouseWheelUnit::Point, delta, modifiers: self.modifiers });
+        }
+    }
+
+    pub fn update_touch(&mut self, id: u32, phase: TouchPhase, position: PositionF32) {
+        use egui::{Event, PointerButton, TouchDeviceId, TouchId};
+
+        let pos = egui::Pos2 { x: position.x, y: position.y };
+        let inside = !self.position_outside_gui(position);
+
+        // Only touches that started over the gui are sent to egui
+        if phase == TouchPhase::Start && !inside {
+            return;
+        }
+
+        let egui_phase = match phase {
+            TouchPhase::Start => egui::TouchPhase::Start,
+            TouchPhase::Move => egui::TouchPhase::Move,
+            TouchPhase::End => egui::TouchPhase::End,
+            TouchPhase::Cancel => egui::TouchPhase::Cancel,
+        };
+
+        self.input.events.push(Event::Touch {
+            device_id: TouchDeviceId(0),
+            id: TouchId(id as u64),
+            phase: egui_phase,
+            pos,
+            force: None
+        });
+
+        // Egui widgets only react to pointer events, so the first finger also drives the pointer
+        if phase == TouchPhase::Start && self.pointer_touch.is_none() {
+            self.pointer_touch = Some(id);
         }
+
+        if self.pointer_touch != Some(id) {
+            return;
+        }
+
+        let modifiers = self.modifiers;
+        match phase {
+            TouchPhase::Start => {
+                self.input.events.push(Event::PointerMoved(pos));
+                self.input.events.push(Event::PointerButton { pos, button: PointerButton::Primary, pressed: true, modifiers });
+            },
+            TouchPhase::Move => {
+                self.input.events.push(Event::PointerMoved(pos));
+            },
+            TouchPhase::End | TouchPhase::Cancel => {
+                self.input.events.push(Event::PointerButton { pos, button: PointerButton::Primary, pressed: false, modifiers });
+                self.input.events.push(Event::PointerGone);
+                self.pointer_touch = None;
+            }
+        }
+
+        self.input.focused = true;
     }
 
-    pub fn update_keys(&mut self, key_name: &str, pressed: bool) {
-        use egui::{Event, Modifiers, Key};
-        let key = Key::from_name(key_name);
+    /// `key_name` is a `KeyboardEvent.code` value (ex: "KeyA", "Digit1", "Enter")
+    pub fn update_keys(&mut self, key_name: &str, pressed: bool, repeat: bool) {
+        use egui::{Event, Key};
+
+        if pressed && self.capture_binding_key(key_name) {
+            return;
+        }
+
+        // Letters codes are prefixed by "Key" which egui doesn't understand
+        let name = key_name.strip_prefix("Key").unwrap_or(key_name);
+        let key = Key::from_name(name);
 
         if let Some(key) = key {
-            self.input.events.push(Event::Key { key, physical_key: None, pressed, repeat: false, modifiers: Modifiers::default() })
+            self.input.events.push(Event::Key { key, physical_key: Some(key), pressed, repeat, modifiers: self.modifiers })
+        }
+    }
+
+    /// Returns true if the key was used as a new binding
+    fn capture_binding_key(&mut self, key_name: &str) -> bool {
+        let Some((action, slot)) = self.rebinding else {
+            return false;
+        };
+
+        if key_name == "Escape" {
+            self.rebinding = None;
+            self.force_repaint = true;
+            return true;
+        }
+
+        // Keys that can't be bound (ex: the meta keys) are ignored
+        let Some(binding) = Binding::key(key_name) else {
+            return true;
+        };
+
+        self.rebinding = None;
+        self.events.push(GuiEvent::SetBinding(action, slot, binding));
+        true
+    }
+
+    pub fn update_modifiers(&mut self, modifiers: KeyModifiers) {
+        self.modifiers = egui::Modifiers {
+            alt: modifiers.alt(),
+            ctrl: modifiers.ctrl(),
+            shift: modifiers.shift(),
+            mac_cmd: false,
+            command: modifiers.ctrl() || modifiers.meta(),
+        };
+
+        self.input.modifiers = self.modifiers;
+    }
+
+    pub fn update_text(&mut self, text: &str) {
+        // Control characters are sent as key events
+        if !text.is_empty() && !text.chars().any(char::is_control) {
+            self.input.events.push(egui::Event::Text(text.to_string()));
+        }
+    }
+
+    pub fn update_composition(&mut self, text: &str, done: bool) {
+        use egui::{Event, ImeEvent};
+
+        if !self.composing {
+            self.input.events.push(Event::Ime(ImeEvent::Enabled));
+            self.composing = true;
+        }
+
+        if done {
+            self.input.events.push(Event::Ime(ImeEvent::Commit(text.to_string())));
+            self.input.events.push(Event::Ime(ImeEvent::Disabled));
+            self.composing = false;
+        } else {
+            self.input.events.push(Event::Ime(ImeEvent::Preedit(text.to_string())));
         }
     }
 
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.ctx.wants_keyboard_input() || self.rebinding.is_some()
+    }
+
     pub fn texture_delta(&mut self) -> egui::TexturesDelta {
         ::std::mem::take(&mut self.output.textures_delta)
     }
 
-    pub fn tesselate(&mut self) -> Vec<egui::ClippedPrimitive> {
+    /// Returns `None` if the shapes are the same as the last tessellated frame
+    pub fn tesselate(&mut self) -> Option<Vec<egui::ClippedPrimitive>> {
         let shapes = std::mem::take(&mut self.output.shapes);
-        self.ctx.tessellate(shapes, self.pixel_per_point)
+        let changed = ::std::mem::take(&mut self.retessellate) || shapes != self.last_shapes;
+        if !changed {
+            return None;
+        }
+
+        let primitives = self.ctx.tessellate(shapes.clone(), self.pixel_per_point);
+        self.last_shapes = shapes;
+        Some(primitives)
     }
 
     pub fn load_font(&mut self, assets: &crate::data::Assets) -> Result<(), crate::Error>  {
         let mut fonts = egui::FontDefinitions::default();
 
         let font_name = "firacode".to_string();
-        let font_data;
-        match assets.fonts.get(&font_name) {
-            Some(data) => { font_data = data; },
+        let font_data = match assets.fonts.get(&font_name) {
+            Some(data) => data,
             None => { return Err(assets_err!("Missing font source for font \"firacode\"")); }
-        }
+        };
 
         fonts.font_data.insert(font_name.clone(), std::sync::Arc::new(  egui::FontData::from_owned(font_data.clone()) ) );
         fonts.families.get_mut(&egui::FontFamily::Proportional).unwrap().push(font_name);
@@ -203,31 +628,22 @@ impl Gui {
     }
 }
 
-impl StoreLoad for Gui {
-    fn store(&mut self, writer: &mut crate::store::StoreWriter) {
-        writer.write(&self.height);
-        writer.write(&self.pixel_per_point);
-        writer.write(&self.max_texture_size);
-        writer.write(&self.view);
-    }
-
-    fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
-        let mut gui = Gui::default();
-        gui.height = reader.try_read()?;
-        gui.pixel_per_point = reader.try_read()?;
-        gui.max_texture_size = reader.try_read()?;
-        gui.view = reader.try_read()?;
-
-        let input = &mut gui.input;
-        input.max_texture_side = Some(gui.max_texture_size as usize);
+impl Gui {
+    /// Rebuilds the egui input from the stored values
+    fn after_load(&mut self) -> Result<(), crate::error::Error> {
+        let input = &mut self.input;
+        input.max_texture_side = Some(self.max_texture_size as usize);
         input.time = Some(0.0);
         input.system_theme = Some(egui::Theme::Dark);
 
-        let min = egui::Pos2 { x: gui.view[0], y: gui.view[1] };
-        let size = egui::Vec2 { x: gui.view[2], y: gui.view[3] };
+        let min = egui::Pos2 { x: self.view[0], y: self.view[1] };
+        let size = egui::Vec2 { x: self.view[2], y: self.view[3] };
         input.screen_rect = Some(egui::Rect::from_min_size(min, size));
 
-        Ok(gui)
+        let pixel_ratio = crate::data::sanitize_pixel_ratio(self.pixel_per_point);
+        self.set_pixel_ratio(pixel_ratio);
+
+        Ok(())
     }
 }
 
@@ -239,13 +655,30 @@ impl Default for Gui {
             input: Box::default(),
             output: Box::default(),
             height: 300.0,
+            collapsed: false,
+            panel_rect: egui::Rect::NOTHING,
+            pointer_inside: false,
             pixel_per_point: 1.0,
             max_texture_size: 2048,
             view: [0.0; 4],
             game_state: GameStateValue::Uninitialized,
             game_input: GameInputType::Select,
             debug_flags: DebugFlags::default(),
+            follow_selection: false,
+            bindings: KeyBindings::default(),
+            icons: GuiIcons::default(),
+            strings: Strings::default(),
+            show_settings: false,
+            rebinding: None,
+            context_menu: None,
+            inspected: None,
+            stats: Box::default(),
+            pointer_touch: None,
+            modifiers: egui::Modifiers::default(),
+            composing: false,
             events: Vec::new(),
+            last_shapes: Vec::new(),
+            retessellate: true,
             force_repaint: true,
         }
     }
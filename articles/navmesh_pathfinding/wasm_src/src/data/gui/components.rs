@@ -1,11 +1,19 @@
 use crate::state::{GameStateValue, GameInputType};
 use crate::data::base::DebugFlags;
-use super::GuiEvent;
+use crate::data::actions::{Action, Binding, KeyBindings, BINDINGS_PER_ACTION};
+use crate::data::world::EntitySnapshot;
+use crate::data::stats::{Stats, StatsPhase, HISTORY_SIZE};
+use crate::data::strings::Strings;
+use super::{GuiEvent, GuiIcon, GuiIcons};
 
 pub(super) struct LeftPanelParams<'a> {
     pub events: &'a mut Vec<GuiEvent>,
     pub state: &'a mut GameStateValue,
     pub state_input: &'a mut GameInputType,
+    pub debug_flags: &'a mut DebugFlags,
+    pub show_settings: &'a mut bool,
+    pub collapsed: &'a mut bool,
+    pub strings: &'a Strings,
     pub panel_width: f32,
 }
 
@@ -13,29 +21,58 @@ pub(super) struct PanelParams<'a> {
     pub events: &'a mut Vec<GuiEvent>,
     pub debug_flags: &'a mut DebugFlags,
     pub state_input: &'a mut GameInputType,
+    pub follow_selection: &'a mut bool,
+    pub icons: &'a GuiIcons,
+    pub strings: &'a Strings,
+}
+
+pub(super) struct SettingsPanelParams<'a> {
+    pub events: &'a mut Vec<GuiEvent>,
+    pub bindings: &'a KeyBindings,
+    pub rebinding: &'a mut Option<(Action, usize)>,
+    pub strings: &'a Strings,
 }
 
 pub fn left_panel(ui: &mut egui::Ui, params: LeftPanelParams) {
+    let s = params.strings;
     egui::SidePanel::left("left_panel")
         .resizable(false)
         .exact_width(params.panel_width)
         .show_inside(ui, |ui| {
             ui.vertical(|ui| {
                 let mut game_state_update = false;
-                game_state_update |= ui.selectable_value(params.state, GameStateValue::Generation, "Generation").clicked();
-                game_state_update |= ui.selectable_value(params.state, GameStateValue::Navigation, "Navigation").clicked();
-                game_state_update |= ui.selectable_value(params.state, GameStateValue::Obstacles, "Obstacles").clicked();
-                game_state_update |= ui.selectable_value(params.state, GameStateValue::FinalDemo, "Final Demo").clicked();
+                game_state_update |= ui.selectable_value(params.state, GameStateValue::Generation, s.get("Generation")).clicked();
+                game_state_update |= ui.selectable_value(params.state, GameStateValue::Navigation, s.get("Navigation")).clicked();
+                game_state_update |= ui.selectable_value(params.state, GameStateValue::Obstacles, s.get("Obstacles")).clicked();
+                game_state_update |= ui.selectable_value(params.state, GameStateValue::FinalDemo, s.get("Final Demo")).clicked();
 
                 if game_state_update {
                     *params.state_input = GameInputType::Select;
+                    *params.show_settings = false;
                     params.events.push(GuiEvent::GameStateValueChanged(*params.state));
                     params.events.push(GuiEvent::SetInputType(GameInputType::Select));
                 }
+
+                ui.separator();
+                ui.toggle_value(params.show_settings, s.get("Key bindings"));
+                bitflag_checkbox(ui, params.events, s.get("Performance"), params.debug_flags, DebugFlags::SHOW_PERFORMANCE);
+
+                ui.separator();
+                if ui.button(s.get("Hide panel")).clicked() {
+                    *params.collapsed = true;
+                }
             });
         });
 }
 
+pub fn collapsed_panel(ui: &mut egui::Ui, s: &Strings, collapsed: &mut bool) {
+    ui.horizontal_centered(|ui| {
+        if ui.button(s.get("Show panel")).clicked() {
+            *collapsed = false;
+        }
+    });
+}
+
 pub fn right_panel<F>(ui: &mut egui::Ui, width: f32, callback: F) 
     where F: FnOnce(&mut egui::Ui)
 {
@@ -47,82 +84,310 @@ pub fn right_panel<F>(ui: &mut egui::Ui, width: f32, callback: F)
 }
 
 pub fn generation_panel(ui: &mut egui::Ui, params: PanelParams) {
+    let s = params.strings;
     ui.vertical(|ui| {
         ui.horizontal(|ui| {
-            if ui.button("Reset World").clicked() {
+            if ui.button(s.get("Reset World")).clicked() {
                 params.events.push(GuiEvent::ResetWorld);
             }
             
             let mut input_update = false;
-            input_update |= ui.selectable_value(params.state_input, GameInputType::Select, "Select").clicked();
-            input_update |= ui.selectable_value(params.state_input, GameInputType::Delete, "Delete").clicked();
-            input_update |= ui.selectable_value(params.state_input, GameInputType::PlacePawn, "Add Pawn").clicked();
-            input_update |= ui.selectable_value(params.state_input, GameInputType::PlaceCastle, "Add Castle").clicked();
-            input_update |= ui.selectable_value(params.state_input, GameInputType::PlaceHouse, "Add House").clicked();
+            input_update |= ui.selectable_value(params.state_input, GameInputType::Select, s.get("Select")).clicked();
+            input_update |= ui.selectable_value(params.state_input, GameInputType::Delete, s.get("Delete")).clicked();
+            input_update |= tool_button(ui, params.state_input, GameInputType::PlacePawn, params.icons.pawn, s.get("Add Pawn"));
+            input_update |= tool_button(ui, params.state_input, GameInputType::PlaceCastle, params.icons.castle, s.get("Add Castle"));
+            input_update |= tool_button(ui, params.state_input, GameInputType::PlaceHouse, params.icons.house, s.get("Add House"));
             if input_update {
                 params.events.push(GuiEvent::SetInputType(*params.state_input));
             }
         });
         ui.separator();
-        bitflag_checkbox(ui, params.events, "Show navmesh", params.debug_flags, DebugFlags::SHOW_NAVMESH);
+        follow_checkbox(ui, params.events, s, params.follow_selection);
+        bitflag_checkbox(ui, params.events, s.get("Show navmesh"), params.debug_flags, DebugFlags::SHOW_NAVMESH);
     });
 }
 
 pub fn navigation_panel(ui: &mut egui::Ui, params: PanelParams) {
+    let s = params.strings;
     ui.vertical(|ui| {
         ui.horizontal(|ui| {
-            if ui.button("Reset Pawn").clicked() {
+            if ui.button(s.get("Reset Pawn")).clicked() {
                 params.events.push(GuiEvent::ResetPawnPosition);
             }
         });
         ui.separator();
-        bitflag_checkbox(ui, params.events, "Show navmesh", params.debug_flags, DebugFlags::SHOW_NAVMESH);
-        bitflag_checkbox(ui, params.events, "Show hovered triangle", params.debug_flags, DebugFlags::SHOW_HOVERED_TRIANGLE);
-        bitflag_checkbox(ui, params.events, "Show cell centers", params.debug_flags, DebugFlags::SHOW_CELL_CENTERS);
-        bitflag_checkbox(ui, params.events, "Show path", params.debug_flags, DebugFlags::SHOW_PATH);
+        bitflag_checkbox(ui, params.events, s.get("Show navmesh"), params.debug_flags, DebugFlags::SHOW_NAVMESH);
+        bitflag_checkbox(ui, params.events, s.get("Show hovered triangle"), params.debug_flags, DebugFlags::SHOW_HOVERED_TRIANGLE);
+        bitflag_checkbox(ui, params.events, s.get("Show cell centers"), params.debug_flags, DebugFlags::SHOW_CELL_CENTERS);
+        bitflag_checkbox(ui, params.events, s.get("Show path"), params.debug_flags, DebugFlags::SHOW_PATH);
     });
 }
 
 pub fn obstacles_panel(ui: &mut egui::Ui, params: PanelParams) {
+    let s = params.strings;
     ui.vertical(|ui| {
         ui.horizontal(|ui| {
-            if ui.button("Reset Pawn").clicked() {
+            if ui.button(s.get("Reset Pawn")).clicked() {
                 params.events.push(GuiEvent::ResetPawnPosition);
             }
         });
         ui.separator();
-        bitflag_checkbox(ui, params.events, "Show navmesh", params.debug_flags, DebugFlags::SHOW_NAVMESH);
-        bitflag_checkbox(ui, params.events, "Show collisions box", params.debug_flags, DebugFlags::SHOW_COLLISION_BOXES);
-        bitflag_checkbox(ui, params.events, "Show blocked cells", params.debug_flags, DebugFlags::SHOW_BLOCKED_CELLS);
-        bitflag_checkbox(ui, params.events, "Show path", params.debug_flags, DebugFlags::SHOW_PATH);
+        bitflag_checkbox(ui, params.events, s.get("Show navmesh"), params.debug_flags, DebugFlags::SHOW_NAVMESH);
+        bitflag_checkbox(ui, params.events, s.get("Show collisions box"), params.debug_flags, DebugFlags::SHOW_COLLISION_BOXES);
+        bitflag_checkbox(ui, params.events, s.get("Show blocked cells"), params.debug_flags, DebugFlags::SHOW_BLOCKED_CELLS);
+        bitflag_checkbox(ui, params.events, s.get("Show path"), params.debug_flags, DebugFlags::SHOW_PATH);
     });
 }
 
 pub fn final_panel(ui: &mut egui::Ui, params: PanelParams) {
+    let s = params.strings;
     ui.vertical(|ui| {
         ui.horizontal(|ui| {
-            if ui.button("Reset World").clicked() {
+            if ui.button(s.get("Reset World")).clicked() {
                 params.events.push(GuiEvent::ResetWorld);
             }
-            if ui.button("Reset Pawn").clicked() {
+            if ui.button(s.get("Reset Pawn")).clicked() {
                 params.events.push(GuiEvent::ResetPawnPosition);
             }
         });
 
         ui.horizontal(|ui| {
             let mut input_update = false;
-            input_update |= ui.selectable_value(params.state_input, GameInputType::Select, "Select").clicked();
-            input_update |= ui.selectable_value(params.state_input, GameInputType::Delete, "Delete").clicked();
-            input_update |= ui.selectable_value(params.state_input, GameInputType::PlacePawn, "Add Pawn").clicked();
-            input_update |= ui.selectable_value(params.state_input, GameInputType::PlaceCastle, "Add Castle").clicked();
-            input_update |= ui.selectable_value(params.state_input, GameInputType::PlaceHouse, "Add House").clicked();
+            input_update |= ui.selectable_value(params.state_input, GameInputType::Select, s.get("Select")).clicked();
+            input_update |= ui.selectable_value(params.state_input, GameInputType::Delete, s.get("Delete")).clicked();
+            input_update |= tool_button(ui, params.state_input, GameInputType::PlacePawn, params.icons.pawn, s.get("Add Pawn"));
+            input_update |= tool_button(ui, params.state_input, GameInputType::PlaceCastle, params.icons.castle, s.get("Add Castle"));
+            input_update |= tool_button(ui, params.state_input, GameInputType::PlaceHouse, params.icons.house, s.get("Add House"));
             if input_update {
                 params.events.push(GuiEvent::SetInputType(*params.state_input));
             }
         });
+        ui.separator();
+        follow_checkbox(ui, params.events, s, params.follow_selection);
+    });
+}
+
+pub fn settings_panel(ui: &mut egui::Ui, params: SettingsPanelParams) {
+    let s = params.strings;
+    ui.horizontal(|ui| {
+        if ui.button(s.get("Reset to defaults")).clicked() {
+            *params.rebinding = None;
+            params.events.push(GuiEvent::ResetBindings);
+        }
+        ui.label(s.get("Click a binding then press a key or a mouse button. Right click to clear. Escape to cancel."));
+    });
+    ui.separator();
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("key_bindings").num_columns(BINDINGS_PER_ACTION + 1).striped(true).show(ui, |ui| {
+            for action in Action::ALL {
+                ui.label(s.get(action.label()));
+
+                let bindings = params.bindings.get(action);
+                for (slot, binding) in bindings.iter().enumerate() {
+                    let waiting = *params.rebinding == Some((action, slot));
+                    let text = match waiting {
+                        true => s.get("Press a key..."),
+                        false => s.get(binding.label()),
+                    };
+
+                    let response = ui.add_sized([140.0, 20.0], egui::Button::new(text).selected(waiting));
+                    if response.clicked() {
+                        *params.rebinding = Some((action, slot));
+                    } else if response.secondary_clicked() {
+                        *params.rebinding = None;
+                        params.events.push(GuiEvent::SetBinding(action, slot, Binding::None));
+                    }
+                }
+
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// Components of the selected entity. Edited values are sent back to the world with `GuiEvent::EditEntity`.
+pub fn inspector_panel(ui: &mut egui::Ui, events: &mut Vec<GuiEvent>, s: &Strings, inspected: &mut Option<EntitySnapshot>) {
+    egui::SidePanel::right("inspector_panel")
+        .resizable(false)
+        .exact_width(280.0)
+        .show_inside(ui, |ui| {
+            let Some(snapshot) = inspected.as_mut() else {
+                ui.label(s.get("No selection"));
+                return;
+            };
+
+            ui.label(format!("{} {}", s.get(snapshot.kind.label()), snapshot.entity.id()));
+            ui.separator();
+
+            // The texcoord of animated sprites is set from the animation frame
+            let animated = snapshot.animation.is_some();
+
+            let mut changed = false;
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("inspector_grid").num_columns(2).show(ui, |ui| {
+                    let sprite = &mut snapshot.sprite;
+
+                    ui.label(s.get("Position"));
+                    ui.horizontal(|ui| {
+                        changed |= ui.add(egui::DragValue::new(&mut sprite.position.x).speed(1.0).prefix("x: ")).changed();
+                        changed |= ui.add(egui::DragValue::new(&mut sprite.position.y).speed(1.0).prefix("y: ")).changed();
+                    });
+                    ui.end_row();
+
+                    ui.label(s.get("Texcoord"));
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(!animated, |ui| {
+                            let texcoord = &mut sprite.texcoord;
+                            changed |= ui.add(egui::DragValue::new(&mut texcoord.left).speed(1.0)).changed();
+                            changed |= ui.add(egui::DragValue::new(&mut texcoord.top).speed(1.0)).changed();
+                            changed |= ui.add(egui::DragValue::new(&mut texcoord.right).speed(1.0)).changed();
+                            changed |= ui.add(egui::DragValue::new(&mut texcoord.bottom).speed(1.0)).changed();
+                        }).response.on_disabled_hover_text(s.get("Set by the animation"));
+                    });
+                    ui.end_row();
+
+                    ui.label(s.get("Flags"));
+                    ui.horizontal(|ui| {
+                        let mut flipped = sprite.flags.flipped();
+                        if ui.checkbox(&mut flipped, s.get("Flipped")).changed() {
+                            match flipped {
+                                true => sprite.flags.set_flipped(),
+                                false => sprite.flags.clear_flipped(),
+                            }
+                            changed = true;
+                        }
+
+                        // Highlight is driven by the selection
+                        let mut highlighted = sprite.flags.highlighted();
+                        ui.add_enabled(false, egui::Checkbox::new(&mut highlighted, s.get("Highlighted")));
+                    });
+                    ui.end_row();
+
+                    if let Some(animation) = snapshot.animation.as_mut() {
+                        let max_frame = animation.max_frame.saturating_sub(1);
+                        ui.label(s.get("Frame"));
+                        changed |= ui.add(egui::Slider::new(&mut animation.current_frame, 0..=max_frame)).changed();
+                        ui.end_row();
+                    }
+
+                    if let Some(order) = snapshot.move_order.as_mut() {
+                        ui.label(s.get("Move target"));
+                        ui.horizontal(|ui| {
+                            changed |= ui.add(egui::DragValue::new(&mut order.target.x).speed(1.0).prefix("x: ")).changed();
+                            changed |= ui.add(egui::DragValue::new(&mut order.target.y).speed(1.0).prefix("y: ")).changed();
+                        });
+                        ui.end_row();
+                    }
+                });
+            });
+
+            if changed {
+                events.push(GuiEvent::EditEntity(*snapshot));
+            }
+        });
+}
+
+/// Stacked graph of the duration of each frame phase, followed by the averages and the output size of the last frame
+pub fn performance_overlay(ui: &mut egui::Ui, s: &Strings, stats: &Stats) {
+    use egui::{Color32, Rect, Sense, Stroke, pos2, vec2};
+
+    const COLORS: [Color32; 4] = [
+        Color32::from_rgb(86, 156, 214),
+        Color32::from_rgb(78, 201, 176),
+        Color32::from_rgb(220, 220, 120),
+        Color32::from_rgb(206, 145, 120),
+    ];
+
+    // Height of the graph represents 2 frames at 60 fps
+    const GRAPH_MAX_MS: f32 = 33.3;
+    const GRAPH_HEIGHT: f32 = 80.0;
+    const BAR_WIDTH: f32 = 2.0;
+
+    egui::Frame::popup(ui.style()).show(ui, |ui| {
+        let (rect, _) = ui.allocate_exact_size(vec2(BAR_WIDTH * HISTORY_SIZE as f32, GRAPH_HEIGHT), Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_black_alpha(120));
+
+        let to_height = |ms: f32| f32::min(ms / GRAPH_MAX_MS, 1.0) * GRAPH_HEIGHT;
+        let target_y = rect.bottom() - to_height(1000.0 / 60.0);
+        painter.hline(rect.x_range(), target_y, Stroke::new(1.0, Color32::from_gray(90)));
+
+        for (index, frame) in stats.history().enumerate() {
+            let left = rect.left() + (index as f32 * BAR_WIDTH);
+            let mut bottom = rect.bottom();
+            for (phase, &ms) in frame.phases.iter().enumerate() {
+                let height = to_height(ms);
+                if height <= 0.0 {
+                    continue;
+                }
+
+                let bar = Rect::from_min_max(pos2(left, bottom - height), pos2(left + BAR_WIDTH, bottom));
+                painter.rect_filled(bar, 0.0, COLORS[phase]);
+                bottom -= height;
+            }
+        }
+
+        let average = stats.average();
+        for phase in StatsPhase::ALL {
+            let index = phase as usize;
+            ui.label(egui::RichText::new(format!("{}: {:.2} ms", s.get(phase.label()), average[index])).color(COLORS[index]));
+        }
+
+        let last = stats.last();
+        ui.label(format!("{}: {:.1} ms", s.get("Frame time"), last.frame_time));
+        ui.label(format!("{}: {}  {}: {:.1} KB", s.get("Messages"), last.messages_count, s.get("Data"), last.data_bytes as f32 / 1024.0));
     });
 }
 
+/// Returns true if a menu entry was clicked
+pub fn context_menu(ui: &mut egui::Ui, events: &mut Vec<GuiEvent>, s: &Strings, entity: hecs::Entity) -> bool {
+    egui::Frame::menu(ui.style()).show(ui, |ui| {
+        ui.set_min_width(100.0);
+
+        let mut clicked = false;
+        if ui.button(s.get("Inspect")).clicked() {
+            events.push(GuiEvent::InspectEntity(entity));
+            clicked = true;
+        }
+        if ui.button(s.get("Duplicate")).clicked() {
+            events.push(GuiEvent::DuplicateEntity(entity));
+            clicked = true;
+        }
+        if ui.button(s.get("Delete")).clicked() {
+            events.push(GuiEvent::DeleteEntity(entity));
+            clicked = true;
+        }
+
+        clicked
+    }).inner
+}
+
+/// Same as `ui.selectable_value`, with an atlas sprite in front of the label
+fn tool_button(ui: &mut egui::Ui, current: &mut GameInputType, value: GameInputType, icon: Option<GuiIcon>, label: &str) -> bool {
+    let Some(icon) = icon else {
+        return ui.selectable_value(current, value, label).clicked();
+    };
+
+    let texture = egui::load::SizedTexture::new(icon.texture, icon.size);
+    let image = egui::Image::from_texture(texture)
+        .uv(icon.uv)
+        .fit_to_exact_size(egui::vec2(18.0, 18.0));
+
+    let clicked = ui.add(egui::Button::image_and_text(image, label).selected(*current == value)).clicked();
+    if clicked {
+        *current = value;
+    }
+
+    clicked
+}
+
+fn follow_checkbox(ui: &mut egui::Ui, events: &mut Vec<GuiEvent>, s: &Strings, follow: &mut bool) {
+    if ui.checkbox(follow, s.get("Follow selection")).changed() {
+        events.push(GuiEvent::SetFollowSelection(*follow));
+    }
+}
+
 fn bitflag_checkbox(
     ui: &mut egui::Ui,
     events: &mut Vec<GuiEvent>,
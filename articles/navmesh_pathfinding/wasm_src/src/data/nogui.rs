@@ -2,6 +2,8 @@
 #![allow(dead_code)]
 
 use crate::data::base::DebugFlags;
+use crate::data::actions::{Action, Binding, KeyBindings};
+use crate::data::world::EntitySnapshot;
 use crate::shared::PositionF32;
 use crate::state::{GameStateValue, GameInputType};
 
@@ -10,6 +12,13 @@ pub enum GuiEvent {
     GameStateValueChanged(GameStateValue),
     SetDebugFlags(DebugFlags),
     SetInputType(GameInputType),
+    SetFollowSelection(bool),
+    SetBinding(Action, usize, Binding),
+    InspectEntity(hecs::Entity),
+    DuplicateEntity(hecs::Entity),
+    DeleteEntity(hecs::Entity),
+    EditEntity(EntitySnapshot),
+    ResetBindings,
     ResetWorld,
     ResetPawnPosition,
 }
@@ -24,17 +33,32 @@ impl Gui {
     }
 
     pub fn update_time(&self, _delta: f32) {}
-    pub fn resize(&mut self, _width: u32, _height: u32) { }
+    pub fn resize(&mut self, _width: u32, _height: u32, _pixel_ratio: f32) { }
     pub fn update(&self) -> bool { false }
     pub fn set_state(&mut self, _state: GameStateValue, _input: GameInputType) {}
     pub fn set_debug_flags(&mut self, _flags: DebugFlags) {}
+    pub fn set_follow_selection(&mut self, _follow: bool) {}
+    pub fn set_bindings(&mut self, _bindings: KeyBindings) {}
+    pub fn set_assets(&mut self, _assets: &crate::data::Assets) {}
+    pub fn capturing_binding(&self) -> bool { false }
+    pub fn open_context_menu(&mut self, _entity: hecs::Entity, _position: PositionF32) {}
+    pub fn close_context_menu(&mut self) {}
+    pub fn set_inspected(&mut self, _snapshot: Option<EntitySnapshot>) {}
+    pub fn set_stats(&mut self, _stats: &crate::data::stats::Stats) {}
     pub fn events(&mut self) -> Vec<GuiEvent> { Vec::new() }
     pub fn clear_events(&mut self) {}
     pub fn load_font(&mut self, _assets: &crate::data::Assets) -> Result<(), crate::Error>  { Ok(()) }
     pub fn load_style(&mut self) {}
     pub fn update_mouse_position(&mut self, _x: f32, _y: f32) {}
     pub fn update_mouse_buttons(&mut self, _position: crate::shared::PositionF32, _button: u8, _pressed: bool) {}
-    pub fn update_keys(&mut self, _key_name: &str, _pressed: bool) { }
+    pub fn update_mouse_wheel(&mut self, _position: PositionF32, _delta_x: f32, _delta_y: f32) {}
+    pub fn update_touch(&mut self, _id: u32, _phase: crate::data::touch::TouchPhase, _position: PositionF32) {}
+    pub fn update_keys(&mut self, _key_name: &str, _pressed: bool, _repeat: bool) { }
+    pub fn update_modifiers(&mut self, _modifiers: crate::data::base::KeyModifiers) {}
+    pub fn update_text(&mut self, _text: &str) {}
+    pub fn update_composition(&mut self, _text: &str, _done: bool) {}
+    pub fn wants_keyboard_input(&self) -> bool { false }
+    pub fn top(&self) -> f32 { f32::MAX }
     pub fn position_inside_gui(&self, _p: PositionF32) -> bool { false }
     pub fn position_outside_gui(&self, _p: PositionF32) -> bool { true }
 }
@@ -47,6 +71,9 @@ impl crate::store::StoreLoad for Gui {
     fn load(_reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
         Ok(Gui::default())
     }
+
+    fn schema(_schema: &mut crate::store::Schema) {
+    }
 }
 
 impl Default for Gui {
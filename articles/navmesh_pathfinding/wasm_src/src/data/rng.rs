@@ -0,0 +1,130 @@
+//! Small deterministic pseudo random number generator (xoshiro128**)
+//! Only uses 32 bits integer operations so the output is the same on native and wasm targets
+use zerocopy_derive::{FromBytes, Immutable, IntoBytes};
+use crate::shared::{PositionF32, AABB, pos};
+use crate::store::StoreLoad;
+
+pub const DEFAULT_SEED: u32 = 0x5EED_1234;
+
+#[derive(Copy, Clone, FromBytes, IntoBytes, Immutable, StoreLoad)]
+pub struct Rng {
+    seed: u32,
+    state: [u32; 4],
+}
+
+impl Rng {
+
+    pub fn new(seed: u32) -> Self {
+        // Expand the seed using splitmix32 so that similar seeds still produce uncorrelated states
+        let mut x = seed;
+        let mut next = || {
+            x = x.wrapping_add(0x9E37_79B9);
+            let mut z = x;
+            z = (z ^ (z >> 16)).wrapping_mul(0x85EB_CA6B);
+            z = (z ^ (z >> 13)).wrapping_mul(0xC2B2_AE35);
+            z ^ (z >> 16)
+        };
+
+        let mut state = [next(), next(), next(), next()];
+
+        // An all zero state would only ever generate zeros
+        if state == [0; 4] {
+            state[0] = 1;
+        }
+
+        Rng { seed, state }
+    }
+
+    /// Restart the sequence with a new seed
+    pub fn reseed(&mut self, seed: u32) {
+        *self = Rng::new(seed);
+    }
+
+    /// The seed used to create this generator
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 9;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(11);
+
+        result
+    }
+
+    /// Returns a value in `[0.0, 1.0)`
+    pub fn next_f32(&mut self) -> f32 {
+        // 24 bits of precision is the most a f32 mantissa can represent exactly
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u32() & 0x8000_0000 != 0
+    }
+
+    /// Returns a value in `[min, max)`. Returns `min` if the range is empty.
+    pub fn range_u32(&mut self, min: u32, max: u32) -> u32 {
+        if max <= min {
+            return min;
+        }
+
+        // Lemire's multiply-shift reduction. The bias is negligible for the ranges used by the demo.
+        let range = (max - min) as u64;
+        min + ((self.next_u32() as u64 * range) >> 32) as u32
+    }
+
+    /// Returns a value in `[min, max)`. Returns `min` if the range is empty.
+    pub fn range_i32(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+
+        let range = max.abs_diff(min) as u64;
+        min.wrapping_add(((self.next_u32() as u64 * range) >> 32) as i32)
+    }
+
+    /// Returns a value in `[min, max)`
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Returns a random point inside `aabb`
+    pub fn point_in_aabb(&mut self, aabb: AABB) -> PositionF32 {
+        let x = self.range_f32(aabb.left, aabb.right);
+        let y = self.range_f32(aabb.top, aabb.bottom);
+        pos(x, y)
+    }
+
+    /// Shuffle `values` in place (Fisher-Yates)
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = self.range_u32(0, (i + 1) as u32) as usize;
+            values.swap(i, j);
+        }
+    }
+
+    /// Returns a random item from `values`, or `None` if `values` is empty
+    pub fn pick<'a, T>(&mut self, values: &'a [T]) -> Option<&'a T> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let index = self.range_u32(0, values.len() as u32) as usize;
+        values.get(index)
+    }
+
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(DEFAULT_SEED)
+    }
+}
@@ -0,0 +1,156 @@
+//! Per frame performance statistics. Displayed by the gui when `DebugFlags::SHOW_PERFORMANCE` is set.
+
+pub const PHASE_COUNT: usize = 4;
+
+/// Number of frames kept in the history
+pub const HISTORY_SIZE: usize = 120;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum StatsPhase {
+    StateUpdate,
+    SpriteOrdering,
+    /// `GameOutput::update`, without the sprite ordering and the gui tessellation it contains
+    Output,
+    GuiTessellation,
+}
+
+impl StatsPhase {
+    pub const ALL: [StatsPhase; PHASE_COUNT] = [
+        StatsPhase::StateUpdate,
+        StatsPhase::SpriteOrdering,
+        StatsPhase::Output,
+        StatsPhase::GuiTessellation,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StatsPhase::StateUpdate => "State update",
+            StatsPhase::SpriteOrdering => "Sprite ordering",
+            StatsPhase::Output => "Output generation",
+            StatsPhase::GuiTessellation => "Gui tessellation",
+        }
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct FrameStats {
+    /// Duration of each phase in milliseconds
+    pub phases: [f32; PHASE_COUNT],
+    /// Time since the last frame in milliseconds
+    pub frame_time: f32,
+    pub messages_count: u32,
+    pub data_bytes: u32,
+}
+
+#[derive(Copy, Clone)]
+pub struct Stats {
+    history: [FrameStats; HISTORY_SIZE],
+    /// Index of the oldest frame in `history`
+    next: usize,
+    current: FrameStats,
+    phase_start: [f64; PHASE_COUNT],
+    /// The phase being timed, and the phase that was active when each phase began
+    active: Option<StatsPhase>,
+    parents: [Option<StatsPhase>; PHASE_COUNT],
+}
+
+impl Stats {
+
+    /// Phases can be nested. The time of a nested phase is not counted in its parent, so the phases of a frame never overlap.
+    pub fn begin(&mut self, phase: StatsPhase) {
+        let time = now();
+        if let Some(parent) = self.active {
+            self.current.phases[parent as usize] += (time - self.phase_start[parent as usize]) as f32;
+        }
+
+        self.parents[phase as usize] = self.active;
+        self.active = Some(phase);
+        self.phase_start[phase as usize] = time;
+    }
+
+    pub fn end(&mut self, phase: StatsPhase) {
+        let time = now();
+        let index = phase as usize;
+        self.current.phases[index] += (time - self.phase_start[index]) as f32;
+
+        self.active = self.parents[index];
+        if let Some(parent) = self.active {
+            self.phase_start[parent as usize] = time;
+        }
+    }
+
+    pub fn set_output(&mut self, messages_count: usize, data_bytes: usize) {
+        self.current.messages_count = messages_count as u32;
+        self.current.data_bytes = data_bytes as u32;
+    }
+
+    /// Saves the current frame in the history. Called at the end of a frame.
+    pub fn finish_frame(&mut self, frame_time: f32) {
+        self.current.frame_time = frame_time;
+        self.history[self.next] = self.current;
+        self.next = (self.next + 1) % HISTORY_SIZE;
+        self.current = FrameStats::default();
+    }
+
+    /// Frames from the oldest to the newest
+    pub fn history(&self) -> impl Iterator<Item=&FrameStats> {
+        self.history[self.next..].iter().chain(self.history[..self.next].iter())
+    }
+
+    pub fn last(&self) -> FrameStats {
+        self.history[(self.next + HISTORY_SIZE - 1) % HISTORY_SIZE]
+    }
+
+    /// Average duration of each phase over the history, in milliseconds
+    pub fn average(&self) -> [f32; PHASE_COUNT] {
+        let mut total = [0.0; PHASE_COUNT];
+        for frame in self.history.iter() {
+            for (total, phase) in total.iter_mut().zip(frame.phases.iter()) {
+                *total += phase;
+            }
+        }
+
+        total.map(|value| value / (HISTORY_SIZE as f32) )
+    }
+
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            history: [FrameStats::default(); HISTORY_SIZE],
+            next: 0,
+            current: FrameStats::default(),
+            phase_start: [0.0; PHASE_COUNT],
+            active: None,
+            parents: [None; PHASE_COUNT],
+        }
+    }
+}
+
+#[cfg(target_arch="wasm32")]
+mod timer {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = performance)]
+        pub fn now() -> f64;
+    }
+}
+
+#[cfg(not(target_arch="wasm32"))]
+mod timer {
+    use std::{sync::OnceLock, time::Instant};
+
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    pub fn now() -> f64 {
+        START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+/// Current time in milliseconds
+fn now() -> f64 {
+    timer::now()
+}
@@ -0,0 +1,89 @@
+//! Localised gui strings.
+//! The english text is used as the lookup key, so english doesn't need a table. Other languages are loaded
+//! from the `STRINGS;language;path` entries of the assets bundle. Each line of a table is `english;translation;`.
+use fnv::FnvHashMap;
+use crate::store::StoreLoad;
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+#[derive(Clone)]
+pub struct Strings {
+    language: String,
+    table: FnvHashMap<String, String>,
+}
+
+impl Strings {
+
+    pub fn load_csv(&mut self, language: &str, csv: &str) {
+        self.language = language.to_string();
+        self.table.clear();
+
+        crate::shared::split_csv::<3, _>(csv, |args| {
+            let text = args[0].trim();
+            let translation = args[1].trim();
+            if !text.is_empty() && !translation.is_empty() {
+                self.table.insert(text.to_string(), translation.to_string());
+            }
+        });
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::with_capacity(self.table.len() * 32);
+        for (text, translation) in self.table.iter() {
+            csv.push_str(text);
+            csv.push(';');
+            csv.push_str(translation);
+            csv.push_str(";\n");
+        }
+        csv
+    }
+
+    /// Returns the translation of `text`, or `text` itself if the current language doesn't define it
+    pub fn get<'a>(&'a self, text: &'a str) -> &'a str {
+        self.table.get(text)
+            .map(|translation| translation.as_str() )
+            .unwrap_or(text)
+    }
+
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Strings {
+            language: DEFAULT_LANGUAGE.to_string(),
+            table: FnvHashMap::default(),
+        }
+    }
+}
+
+const LANGUAGE_TAG: u32 = crate::store::tag("language");
+const CSV_TAG: u32 = crate::store::tag("csv");
+
+impl StoreLoad for Strings {
+    fn store(&mut self, writer: &mut crate::store::StoreWriter) {
+        writer.write_section("language", |writer| writer.write_str(&self.language) );
+        writer.write_section("csv", |writer| writer.write_str(&self.to_csv()) );
+    }
+
+    fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
+        let mut strings = Strings::default();
+        let mut language = DEFAULT_LANGUAGE;
+        let mut csv = "";
+        reader.read_sections(|reader, tag| {
+            match tag {
+                LANGUAGE_TAG => { language = reader.read_str()?; },
+                CSV_TAG => { csv = reader.read_str()?; },
+                _ => { return Ok(false); }
+            }
+            Ok(true)
+        })?;
+
+        strings.load_csv(language, csv);
+        Ok(strings)
+    }
+
+    fn schema(schema: &mut crate::store::Schema) {
+        schema.string("language")
+            .string("csv");
+    }
+}
@@ -1,12 +1,18 @@
 use zerocopy_derive::{Immutable, IntoBytes, TryFromBytes};
+use crate::shared::{SizeF32, size};
 use crate::store::StoreLoad;
 
+/// Size of a terrain cell in world units
+pub const TERRAIN_CELL_SIZE: f32 = 64.0;
+
 #[derive(Copy, Clone, TryFromBytes, IntoBytes, Immutable)]
 #[repr(u8)]
 pub enum TerrainCell {
     Grass,
 }
 
+#[derive(Default, StoreLoad)]
+#[store(after_load = Terrain::after_load)]
 pub struct Terrain {
     width: u32,
     height: u32,
@@ -33,31 +39,21 @@ impl Terrain {
         self.height
     }
 
-}
-
-impl StoreLoad for Terrain {
-    fn store(&mut self, writer: &mut crate::store::StoreWriter) {
-        writer.write(&self.width);
-        writer.write(&self.height);
-        writer.write_array(&self.cells);
+    /// Size of the terrain in world units
+    pub fn world_size(&self) -> SizeF32 {
+        size(self.width as f32 * TERRAIN_CELL_SIZE, self.height as f32 * TERRAIN_CELL_SIZE)
     }
 
-    fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
-        let mut terrain = Terrain::default();
-        terrain.width = reader.try_read()?;
-        terrain.height = reader.try_read()?;
-        terrain.cells = unsafe { reader.read_array_transmute().to_vec() };
-        Ok(terrain)
-    }
 }
 
-impl Default for Terrain {
-    fn default() -> Terrain {
-        Terrain {
-            width: 0,
-            height: 0,
-            cells: Vec::new()
+impl Terrain {
+    fn after_load(&mut self) -> Result<(), crate::error::Error> {
+        if self.cells.len() != (self.width as usize) * (self.height as usize) {
+            return Err(save_err!("Terrain has {} cells, but its size is {}x{}", self.cells.len(), self.width, self.height));
         }
+
+        Ok(())
     }
 }
 
+
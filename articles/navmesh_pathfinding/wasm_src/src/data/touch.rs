@@ -0,0 +1,128 @@
+//! Multi-touch tracking. Touch positions are in screen space.
+use crate::shared::PositionF32;
+
+pub const MAX_TOUCHES: usize = 4;
+
+/// Maximum distance in pixels a finger can travel for a touch to still count as a tap
+const TAP_MAX_DISTANCE: f32 = 12.0;
+
+/// Maximum duration in milliseconds of a tap
+const TAP_MAX_DURATION: f64 = 350.0;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum TouchPhase {
+    Start,
+    Move,
+    End,
+    Cancel,
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct TouchPoint {
+    pub id: u32,
+    pub active: bool,
+    pub position: PositionF32,
+    pub position_old: PositionF32,
+    start_position: PositionF32,
+    start_time: f64,
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct TouchState {
+    points: [TouchPoint; MAX_TOUCHES],
+    /// Position of a one finger tap completed since the last frame
+    tap: Option<PositionF32>,
+    /// Set once a second finger touches the screen so that lifting the fingers doesn't generate a tap
+    multi_touch: bool,
+}
+
+impl TouchState {
+
+    pub fn start(&mut self, id: u32, position: PositionF32, time: f64) {
+        // Touches over the maximum are ignored
+        let Some(point) = self.points.iter_mut().find(|point| !point.active ) else {
+            return;
+        };
+
+        *point = TouchPoint {
+            id,
+            active: true,
+            position,
+            position_old: position,
+            start_position: position,
+            start_time: time,
+        };
+
+        self.multi_touch |= self.active_count() > 1;
+    }
+
+    pub fn moved(&mut self, id: u32, position: PositionF32) {
+        if let Some(point) = self.find_mut(id) {
+            point.position = position;
+        }
+    }
+
+    pub fn end(&mut self, id: u32, position: PositionF32, time: f64) {
+        let Some(point) = self.find_mut(id) else {
+            return;
+        };
+
+        point.active = false;
+        point.position = position;
+
+        let is_tap = (position - point.start_position).length() <= TAP_MAX_DISTANCE
+            && (time - point.start_time) <= TAP_MAX_DURATION;
+
+        if is_tap && !self.multi_touch {
+            self.tap = Some(position);
+        }
+
+        if self.active_count() == 0 {
+            self.multi_touch = false;
+        }
+    }
+
+    pub fn cancel(&mut self, id: u32) {
+        if let Some(point) = self.find_mut(id) {
+            point.active = false;
+        }
+
+        if self.active_count() == 0 {
+            self.multi_touch = false;
+        }
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.points.iter().filter(|point| point.active ).count()
+    }
+
+    /// Returns the first two active touches if exactly two fingers are on the screen
+    pub fn two_fingers(&self) -> Option<[TouchPoint; 2]> {
+        if self.active_count() != 2 {
+            return None;
+        }
+
+        let mut active = self.points.iter().filter(|point| point.active );
+        match (active.next(), active.next()) {
+            (Some(&a), Some(&b)) => Some([a, b]),
+            _ => None
+        }
+    }
+
+    /// Returns the position of the last one finger tap, if any, and clears it
+    pub fn take_tap(&mut self) -> Option<PositionF32> {
+        self.tap.take()
+    }
+
+    /// Called at the end of a frame
+    pub fn finalize(&mut self) {
+        for point in self.points.iter_mut() {
+            point.position_old = point.position;
+        }
+    }
+
+    fn find_mut(&mut self, id: u32) -> Option<&mut TouchPoint> {
+        self.points.iter_mut().find(|point| point.active && point.id == id )
+    }
+
+}
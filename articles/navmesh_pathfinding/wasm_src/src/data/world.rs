@@ -1,20 +1,71 @@
+mod serialize;
+
 use hecs::{Entity, World as HecsWorld};
-use zerocopy::transmute;
 use zerocopy_derive::{Immutable, IntoBytes, FromBytes};
-use crate::shared::{PositionF32, AABB};
+use crate::shared::{PositionF32, AABB, pos};
 use crate::store::StoreLoad;
-use super::base::{BaseSprite, BaseSpriteFlags, AnimationState, StaticSprite};
+use serialize::StoredComponent;
+use super::base::{BaseSprite, BaseSpriteFlags, AnimationState, AnimatedSprite, StaticSprite};
+
+/// Pawn walking speed in world units per seconds
+const PAWN_SPEED: f32 = 140.0;
 
 #[derive(Default)] pub struct IsPawn;
 #[derive(Default)] pub struct IsCastle;
 #[derive(Default)] pub struct IsHouse;
 
+/// Components saved with the world. The names are stored in the saves and must not change.
+/// Components that are not listed here are lost when the game client is reloaded.
+const STORED_COMPONENTS: &[StoredComponent] = &[
+    StoredComponent::marker::<IsPawn>("pawn"),
+    StoredComponent::marker::<IsCastle>("castle"),
+    StoredComponent::marker::<IsHouse>("house"),
+    StoredComponent::value::<BaseSprite>("base_sprite"),
+    StoredComponent::value::<AnimationState>("animation_state"),
+    StoredComponent::value::<MoveOrder>("move_order"),
+];
+
+/// A pawn walking toward `target`. `target` is the final position of the pawn sprite.
+#[derive(Copy, Clone, PartialEq, IntoBytes, FromBytes, Immutable)]
+pub struct MoveOrder {
+    pub target: PositionF32,
+}
+
 #[derive(Copy, Clone, IntoBytes, FromBytes, Immutable)]
 pub struct InsertSprite {
     pub position: PositionF32,
     pub sprite: AABB,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum EntityKind {
+    Pawn,
+    House,
+    Castle,
+    Unknown,
+}
+
+impl EntityKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            EntityKind::Pawn => "Pawn",
+            EntityKind::House => "House",
+            EntityKind::Castle => "Castle",
+            EntityKind::Unknown => "Entity",
+        }
+    }
+}
+
+/// Copy of the components of an entity. Used by the gui inspector.
+#[derive(Copy, Clone, PartialEq)]
+pub struct EntitySnapshot {
+    pub entity: Entity,
+    pub kind: EntityKind,
+    pub sprite: BaseSprite,
+    pub animation: Option<AnimationState>,
+    pub move_order: Option<MoveOrder>,
+}
+
 #[derive(Copy, Clone)]
 pub struct OrderedSprite {
     pub e: Entity,
@@ -54,17 +105,151 @@ impl World {
     }
 
     pub fn delete_sprite_at_position(&mut self, position: PositionF32) {
-        if let Some(e1) = self.sprite_at_position(position) {
-            if let Some(index) = self.selected_sprites.iter().position(|&e2| e2 == e1 ) {
-                self.selected_sprites.remove(index);
+        if let Some(entity) = self.sprite_at_position(position) {
+            self.delete_sprite(entity);
+        }
+    }
+
+    pub fn delete_sprite(&mut self, entity: Entity) {
+        if let Some(index) = self.selected_sprites.iter().position(|&e2| e2 == entity ) {
+            self.selected_sprites.remove(index);
+        }
+
+        if let Err(err) = self.inner.despawn(entity) {
+            warn!("Failed to remove entity {:?}", err);
+        }
+    }
+
+    /// Spawns a copy of `entity` moved by `offset`. The copy is not highlighted and doesn't keep the move orders.
+    pub fn duplicate_sprite(&mut self, entity: Entity, offset: PositionF32) -> Option<Entity> {
+        let mut sprite = *self.inner.get::<&BaseSprite>(entity).ok()?;
+        sprite.position += offset;
+        sprite.flags.clear_highlighted();
+        sprite.highlight_color = [0; 3];
+
+        let animation = self.inner.get::<&AnimationState>(entity).ok().map(|animation| *animation );
+        let copy = if self.inner.satisfies::<&IsPawn>(entity).unwrap_or(false) {
+            self.inner.spawn((IsPawn, sprite, animation.unwrap_or_default()))
+        } else if self.inner.satisfies::<&IsHouse>(entity).unwrap_or(false) {
+            self.inner.spawn((IsHouse, sprite))
+        } else if self.inner.satisfies::<&IsCastle>(entity).unwrap_or(false) {
+            self.inner.spawn((IsCastle, sprite))
+        } else {
+            return None;
+        };
+
+        Some(copy)
+    }
+
+    pub fn entity_snapshot(&self, entity: Entity) -> Option<EntitySnapshot> {
+        let sprite = *self.inner.get::<&BaseSprite>(entity).ok()?;
+        let animation = self.inner.get::<&AnimationState>(entity).ok().map(|animation| *animation );
+        let move_order = self.inner.get::<&MoveOrder>(entity).ok().map(|order| *order );
+
+        let kind = if self.inner.satisfies::<&IsPawn>(entity).unwrap_or(false) {
+            EntityKind::Pawn
+        } else if self.inner.satisfies::<&IsHouse>(entity).unwrap_or(false) {
+            EntityKind::House
+        } else if self.inner.satisfies::<&IsCastle>(entity).unwrap_or(false) {
+            EntityKind::Castle
+        } else {
+            EntityKind::Unknown
+        };
+
+        Some(EntitySnapshot { entity, kind, sprite, animation, move_order })
+    }
+
+    /// Writes the components of `snapshot` back into the world. Components the entity doesn't have are ignored.
+    pub fn apply_snapshot(&mut self, snapshot: &EntitySnapshot) {
+        let entity = snapshot.entity;
+        let Ok(mut sprite) = self.inner.get::<&mut BaseSprite>(entity) else {
+            return;
+        };
+
+        *sprite = snapshot.sprite;
+
+        if let (Some(value), Ok(mut animation)) = (snapshot.animation, self.inner.get::<&mut AnimationState>(entity)) {
+            animation.current_frame = value.current_frame.min(animation.max_frame.saturating_sub(1));
+            sprite.texcoord = animation.current_frame();
+        }
+
+        if let (Some(value), Ok(mut order)) = (snapshot.move_order, self.inner.get::<&mut MoveOrder>(entity)) {
+            *order = value;
+        }
+    }
+
+    /// Sends the selected pawns toward `target`. `target` is the position of the pawns feet.
+    /// Returns the number of pawns that received the order.
+    pub fn order_selected_pawns_move(&mut self, target: PositionF32) -> usize {
+        let mut count = 0;
+        for &entity in self.selected_sprites.iter() {
+            let Ok(query) = self.inner.query_one_mut::<(&IsPawn, &BaseSprite)>(entity) else {
+                continue;
+            };
+
+            let size = query.1.texcoord.size();
+            let target = pos(target.x - (size.width * 0.5), target.y - size.height);
+            self.inner.insert_one(entity, MoveOrder { target }).ok();
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Moves the pawns with a move order. Pawns use the `walk` animation while moving and the `idle` animation once they arrive.
+    pub fn update_move_orders(&mut self, seconds: f32, idle: AnimatedSprite, walk: AnimatedSprite) {
+        let mut arrived: Vec<Entity> = Vec::new();
+        let step = PAWN_SPEED * seconds;
+
+        for (entity, (sprite, animation, order)) in self.inner.query_mut::<(&mut BaseSprite, &mut AnimationState, &MoveOrder)>() {
+            let delta = order.target - sprite.position;
+            let distance = delta.length();
+            if distance <= step {
+                sprite.position = order.target;
+                *animation = idle.animate();
+                sprite.texcoord = animation.current_frame();
+                arrived.push(entity);
+                continue;
             }
 
-            if let Err(err) = self.inner.despawn(e1) {
-                dbg!("Failed to remove entity {:?}", err);
+            sprite.position += delta * (step / distance);
+
+            if animation.y != walk.sprite_base.top {
+                *animation = walk.animate();
+                sprite.texcoord = animation.current_frame();
+            }
+
+            if delta.x < 0.0 {
+                sprite.flags.set_flipped();
+            } else if delta.x > 0.0 {
+                sprite.flags.clear_flipped();
+            }
+        }
+
+        for entity in arrived {
+            self.inner.remove_one::<MoveOrder>(entity).ok();
+        }
+    }
+
+    pub fn delete_selected_sprites(&mut self) {
+        for entity in self.selected_sprites.drain(..) {
+            if let Err(err) = self.inner.despawn(entity) {
+                warn!("Failed to remove entity {:?}", err);
             }
         }
     }
 
+    pub fn selected_sprites(&self) -> &[Entity] {
+        &self.selected_sprites
+    }
+
+    /// Returns the center of the sprite of `entity` in world space, or `None` if the entity does not exist anymore
+    pub fn sprite_center(&self, entity: Entity) -> Option<PositionF32> {
+        self.inner.get::<&BaseSprite>(entity)
+            .map(|sprite| sprite.rect().center() )
+            .ok()
+    }
+
     pub fn clear_selected_sprites(&mut self) {
         if self.selected_sprites.is_empty() {
             return;
@@ -82,12 +267,19 @@ impl World {
 
     pub fn select_sprite_at_position(&mut self, position: PositionF32) {
         if let Some(entity) = self.sprite_at_position(position) {
-            if let Ok(mut sprite) = self.inner.get::<&mut BaseSprite>(entity) {
-                sprite.flags.set_highlighted();
-                sprite.highlight_color = [255; 3];
-                self.selected_sprites.push(entity);
-                // dbg!("Selected {:?}", entity);
-            }
+            self.select_sprite(entity);
+        }
+    }
+
+    pub fn select_sprite(&mut self, entity: Entity) {
+        if self.selected_sprites.contains(&entity) {
+            return;
+        }
+
+        if let Ok(mut sprite) = self.inner.get::<&mut BaseSprite>(entity) {
+            sprite.flags.set_highlighted();
+            sprite.highlight_color = [255; 3];
+            self.selected_sprites.push(entity);
         }
     }
 
@@ -184,93 +376,32 @@ impl World {
 
 }
 
+const ARCHETYPES_TAG: u32 = crate::store::tag("archetypes");
+const INSERT_SPRITE_TAG: u32 = crate::store::tag("insert_sprite");
+
 impl StoreLoad for World {
     fn store(&mut self, writer: &mut crate::store::StoreWriter) {
-        let mut sprites = Vec::with_capacity(16);
-        store_actors_animated::<&IsPawn>(writer, &mut self.inner, &mut sprites);
-        store_actors::<&IsHouse>(writer, &mut self.inner, &mut sprites);
-        store_actors::<&IsCastle>(writer, &mut self.inner, &mut sprites);
-        writer.write_option(&self.insert_sprite);
+        writer.write_section("archetypes", |writer| serialize::store_world(writer, &self.inner, STORED_COMPONENTS) );
+        writer.write_section("insert_sprite", |writer| writer.write_option(&self.insert_sprite) );
     }
 
     fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
         let mut world = World::default();
-        spawn_actors_animated::<IsPawn>(reader, &mut world.inner);
-        spawn_actors::<IsHouse>(reader, &mut world.inner);
-        spawn_actors::<IsCastle>(reader, &mut world.inner);
-        world.insert_sprite = reader.try_read_option()?;
-        Ok(world)
-    }
-}
-
-//
-// Store / Load
-//
-
-#[derive(Copy, Clone, FromBytes, IntoBytes, Immutable)]
-pub struct EncodeActor {
-    entity: [u32; 2],
-    sprite: BaseSprite,
-    animate: AnimationState,
-}
-
-fn store_actors_animated<T: hecs::Query>(
-    writer: &mut crate::store::StoreWriter,
-    world: &mut HecsWorld,
-    sprites: &mut Vec<EncodeActor>,
-) {
-    
-    for (entity, (_, &sprite, &animate)) in world.query_mut::<(T, &BaseSprite, &AnimationState)>() {
-        sprites.push(EncodeActor {
-            entity: transmute!(entity.to_bits()),
-            sprite,
-            animate
-        });
-    }
-
-    writer.write_array(&sprites);
-    sprites.clear();
-}
-
-fn store_actors<T: hecs::Query>(
-    writer: &mut crate::store::StoreWriter,
-    world: &mut HecsWorld,
-    sprites: &mut Vec<EncodeActor>,
-) {
-    
-    for (entity, (_, &sprite)) in world.query_mut::<(T, &BaseSprite)>() {
-        sprites.push(EncodeActor {
-            entity: transmute!(entity.to_bits()),
-            sprite,
-            animate: Default::default(),
-        });
-    }
-
-    writer.write_array(&sprites);
-    sprites.clear();
-}
+        reader.read_sections(|reader, tag| {
+            match tag {
+                ARCHETYPES_TAG => serialize::load_world(reader, &mut world.inner, STORED_COMPONENTS)?,
+                INSERT_SPRITE_TAG => { world.insert_sprite = reader.try_read_option()?; },
+                _ => { return Ok(false); }
+            }
+            Ok(true)
+        })?;
 
-fn spawn_actors_animated<T: hecs::Component + Default>(
-    reader: &mut crate::store::StoreReader,
-    world: &mut HecsWorld,
-) {
-    let actors = reader.read_array::<EncodeActor>();
-    world.reserve::<(T, BaseSprite, AnimationState)>(actors.len() as u32);
-    for actor in actors.iter() {
-        let entity = Entity::from_bits(transmute!(actor.entity)).expect("Corrupted entity data");
-        world.spawn_at(entity, (T::default(), actor.sprite, actor.animate));
+        Ok(world)
     }
-}
 
-fn spawn_actors<T: hecs::Component + Default>(
-    reader: &mut crate::store::StoreReader,
-    world: &mut HecsWorld,
-) {
-    let actors = reader.read_array::<EncodeActor>();
-    world.reserve::<(T, BaseSprite)>(actors.len() as u32);
-    for actor in actors.iter() {
-        let entity = Entity::from_bits(transmute!(actor.entity)).expect("Corrupted entity data");
-        world.spawn_at(entity, (T::default(), actor.sprite));
+    fn schema(schema: &mut crate::store::Schema) {
+        serialize::schema_world(schema, STORED_COMPONENTS);
+        schema.field::<InsertSprite>("insert_sprite");
     }
 }
 
@@ -0,0 +1,191 @@
+//! Generic store/load of the hecs world.
+//! Every archetype is stored in its own section with the ids of its entities and one section per registered component.
+//! Components are registered in `STORED_COMPONENTS` (world.rs) with a stable name, so adding a component doesn't
+//! require changes here. Entity ids are preserved with `spawn_at`.
+use hecs::{Archetype, Component, Entity, EntityBuilder, World as HecsWorld};
+use zerocopy::{transmute, Immutable, IntoBytes, TryFromBytes};
+use crate::error::Error;
+use crate::store::{tag, Schema, StoreReader, StoreWriter};
+
+/// Corrupted saves could contain entity ids that would make hecs allocate gigabytes of entity slots
+const MAX_LOADED_ENTITY_INDEX: u32 = 0x10_0000;
+
+const ARCHETYPE_TAG: u32 = tag("archetype");
+const ENTITIES_TAG: u32 = tag("entities");
+
+/// A component type saved with the world
+pub struct StoredComponent {
+    /// Name of the component in the saves. Must never change.
+    pub name: &'static str,
+    has: fn(&Archetype) -> bool,
+    store: fn(&Archetype, &mut StoreWriter),
+    load: fn(&mut StoreReader) -> Result<Box<dyn LoadedColumn>, Error>,
+    schema: fn(&mut Schema, &str),
+}
+
+impl StoredComponent {
+
+    /// A component stored by copying its bytes
+    pub const fn value<T: Component + Copy + IntoBytes + TryFromBytes + Immutable>(name: &'static str) -> Self {
+        StoredComponent {
+            name,
+            has: Archetype::has::<T>,
+            store: store_values::<T>,
+            load: load_values::<T>,
+            schema: |schema, name| { schema.field::<T>(name); },
+        }
+    }
+
+    /// A zero sized component that only marks an entity. Only the number of entities is stored.
+    pub const fn marker<T: Component + Default>(name: &'static str) -> Self {
+        StoredComponent {
+            name,
+            has: Archetype::has::<T>,
+            store: store_marker,
+            load: load_marker::<T>,
+            schema: |schema, name| { schema.field::<()>(name); },
+        }
+    }
+
+}
+
+/// A column of components read from a save
+trait LoadedColumn {
+    fn len(&self) -> usize;
+    fn add_to(&self, index: usize, builder: &mut EntityBuilder);
+}
+
+impl<T: Component + Copy> LoadedColumn for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn add_to(&self, index: usize, builder: &mut EntityBuilder) {
+        builder.add(self[index]);
+    }
+}
+
+struct MarkerColumn<T> {
+    count: usize,
+    marker: ::std::marker::PhantomData<T>,
+}
+
+impl<T: Component + Default> LoadedColumn for MarkerColumn<T> {
+    fn len(&self) -> usize {
+        self.count
+    }
+
+    fn add_to(&self, _index: usize, builder: &mut EntityBuilder) {
+        builder.add(T::default());
+    }
+}
+
+fn store_values<T: Component + Copy + IntoBytes + Immutable>(archetype: &Archetype, writer: &mut StoreWriter) {
+    if let Some(column) = archetype.get::<&T>() {
+        writer.write_array(&column);
+    }
+}
+
+fn load_values<T: Component + Copy + TryFromBytes + Immutable>(reader: &mut StoreReader) -> Result<Box<dyn LoadedColumn>, Error> {
+    let values: Vec<T> = reader.read_array()?;
+    Ok(Box::new(values))
+}
+
+fn store_marker(archetype: &Archetype, writer: &mut StoreWriter) {
+    writer.write(&archetype.len());
+}
+
+fn load_marker<T: Component + Default>(reader: &mut StoreReader) -> Result<Box<dyn LoadedColumn>, Error> {
+    let count: u32 = reader.try_read()?;
+    Ok(Box::new(MarkerColumn::<T> { count: count as usize, marker: ::std::marker::PhantomData }))
+}
+
+pub fn store_world(writer: &mut StoreWriter, world: &HecsWorld, components: &[StoredComponent]) {
+    for archetype in world.archetypes().filter(|archetype| !archetype.is_empty() ) {
+        writer.write_section("archetype", |writer| store_archetype(writer, world, archetype, components) );
+    }
+}
+
+fn store_archetype(writer: &mut StoreWriter, world: &HecsWorld, archetype: &Archetype, components: &[StoredComponent]) {
+    let entities: Vec<[u32; 2]> = archetype.ids().iter()
+        // Safety: the ids of an archetype are always alive
+        .map(|&id| unsafe { world.find_entity_from_id(id) } )
+        .map(|entity| transmute!(entity.to_bits()) )
+        .collect();
+
+    writer.write_section("entities", |writer| writer.write_array(&entities) );
+
+    let mut stored = 0;
+    for component in components.iter().filter(|component| (component.has)(archetype) ) {
+        writer.write_section(component.name, |writer| (component.store)(archetype, writer) );
+        stored += 1;
+    }
+
+    let total = archetype.component_types().len();
+    if stored != total {
+        warn!("{} components of an archetype are not registered in STORED_COMPONENTS and won't be saved", total - stored);
+    }
+}
+
+pub fn load_world(reader: &mut StoreReader, world: &mut HecsWorld, components: &[StoredComponent]) -> Result<(), Error> {
+    reader.read_sections(|reader, section_tag| {
+        match section_tag {
+            ARCHETYPE_TAG => load_archetype(reader, world, components)?,
+            _ => { return Ok(false); }
+        }
+        Ok(true)
+    })
+}
+
+fn load_archetype(reader: &mut StoreReader, world: &mut HecsWorld, components: &[StoredComponent]) -> Result<(), Error> {
+    let offset = reader.data_offset;
+    let mut entities: Vec<[u32; 2]> = Vec::new();
+    let mut columns: Vec<Box<dyn LoadedColumn>> = Vec::with_capacity(components.len());
+
+    reader.read_sections(|reader, section_tag| {
+        if section_tag == ENTITIES_TAG {
+            entities = reader.read_array()?;
+            return Ok(true);
+        }
+
+        // Components that were removed from the registry are skipped
+        let Some(component) = components.iter().find(|component| tag(component.name) == section_tag ) else {
+            return Ok(false);
+        };
+
+        columns.push((component.load)(reader)?);
+        Ok(true)
+    })?;
+
+    if let Some(column) = columns.iter().find(|column| column.len() != entities.len() ) {
+        return Err(save_err!("Archetype at offset {} has {} entities, but a component column has {} values", offset, entities.len(), column.len()));
+    }
+
+    let mut builder = EntityBuilder::new();
+    for (index, &bits) in entities.iter().enumerate() {
+        let entity = decode_entity(bits)?;
+        for column in columns.iter() {
+            column.add_to(index, &mut builder);
+        }
+
+        world.spawn_at(entity, builder.build());
+    }
+
+    Ok(())
+}
+
+pub fn schema_world(schema: &mut Schema, components: &[StoredComponent]) {
+    for component in components {
+        (component.schema)(schema, component.name);
+    }
+}
+
+pub fn decode_entity(bits: [u32; 2]) -> Result<Entity, Error> {
+    let [index, generation] = bits;
+    if index >= MAX_LOADED_ENTITY_INDEX {
+        return Err(save_err!("Entity index {} is too large", index));
+    }
+
+    Entity::from_bits(transmute!(bits))
+        .ok_or_else(|| save_err!("Invalid entity (index {}, generation {})", index, generation) )
+}
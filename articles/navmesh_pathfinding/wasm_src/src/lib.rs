@@ -1,3 +1,4 @@
+
 #[macro_use]
 mod logging;
 
@@ -5,15 +6,25 @@ mod logging;
 mod error;
 
 
+#[macro_use]
+mod store;
+
 mod shared;
 mod data;
 mod state;
 mod output;
-mod store;
+mod recording;
+
+#[cfg(not(target_arch="wasm32"))]
+pub mod native;
+
+pub use data::rng::Rng;
+pub use shared::{AABB, PositionF32};
 
 use fnv::FnvHashMap;
 use error::Error;
 use store::StoreLoad;
+use recording::InputEvent;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -21,8 +32,13 @@ pub struct GameClientInit {
     pub(crate) assets_bundle: String,
     pub(crate) text_assets: FnvHashMap<String, String>,
     pub(crate) bin_assets: FnvHashMap<String, Vec<u8>>,
+    pub(crate) texture_sizes: FnvHashMap<String, [u32; 2]>,
     pub(crate) max_texture_size: u32,
     pub(crate) view_size: shared::SizeF32,
+    pub(crate) pixel_ratio: f32,
+    pub(crate) language: String,
+    pub(crate) seed: u32,
+    pub(crate) record_inputs: bool,
 }
 
 #[wasm_bindgen]
@@ -33,8 +49,13 @@ impl GameClientInit {
             assets_bundle: String::new(),
             text_assets: FnvHashMap::default(),
             bin_assets: FnvHashMap::default(),
+            texture_sizes: FnvHashMap::default(),
             max_texture_size: 2048,
             view_size: shared::size(0.0, 0.0),
+            pixel_ratio: 1.0,
+            language: data::strings::DEFAULT_LANGUAGE.to_string(),
+            seed: data::rng::DEFAULT_SEED,
+            record_inputs: false,
         }
     }
 
@@ -50,6 +71,11 @@ impl GameClientInit {
         self.bin_assets.insert(name, data);
     }
 
+    /// Size in pixels of a texture declared in the assets bundle. Used to compute the uvs of the gui icons.
+    pub fn texture_size(&mut self, name: String, width: u32, height: u32) {
+        self.texture_sizes.insert(name, [width, height]);
+    }
+
     pub fn max_texture_size(&mut self, value: u32) {
         self.max_texture_size = u32::min(value, 4096); // We don't need more than 4096px
     }
@@ -59,6 +85,31 @@ impl GameClientInit {
         self.view_size.height = height;
     }
 
+    /// Number of physical pixels per css pixel (`window.devicePixelRatio`). `view_size` stays in css pixels.
+    pub fn pixel_ratio(&mut self, value: f32) {
+        self.pixel_ratio = data::sanitize_pixel_ratio(value);
+    }
+
+    /// Language of the gui. Must match a `STRINGS` entry of the assets bundle, otherwise the gui stays in english.
+    pub fn language(&mut self, value: String) {
+        self.language = value;
+    }
+
+    pub fn seed(&mut self, value: u32) {
+        self.seed = value;
+    }
+
+    /// Records every input call of the client, see `GameClient::recording`
+    pub fn record_inputs(&mut self, value: bool) {
+        self.record_inputs = value;
+    }
+
+}
+
+impl Default for GameClientInit {
+    fn default() -> Self {
+        GameClientInit::new()
+    }
 }
 
 
@@ -69,16 +120,24 @@ pub struct GameClient {
     data: data::GameData,
     state: state::GameState,
     output: output::GameOutput,
+    /// Set when `load` failed and returned an empty client. The engine must call `initialize` again.
+    needs_init: bool,
+    /// Set when the client was initialized with `GameClientInit::record_inputs`
+    recorder: Option<recording::Recorder>,
+    /// Set when the client was created by `replay`. Inputs from the engine are ignored.
+    replay: Option<recording::Replay>,
 }
 
 #[wasm_bindgen]
 impl GameClient {
     pub fn initialize(init: GameClientInit) -> Option<Self> {
-        ::std::panic::set_hook(Box::new(logging::panic_handler));
+        logging::set_panic_hook();
 
         let mut client = GameClient::default();
 
         client.data.globals.view_size = init.view_size;
+        client.data.globals.pixel_ratio = init.pixel_ratio;
+        client.data.rng = data::rng::Rng::new(init.seed);
 
         if let Err(e) = client.data.assets.init(&init) {
             log_err!(e);
@@ -90,10 +149,159 @@ impl GameClient {
             return None;
         }
 
+        client.data.set_bindings(client.data.assets.key_bindings);
+
+        if init.record_inputs {
+            client.recorder = Some(recording::Recorder::new(&init));
+        }
+
+        Some(client)
+    }
+
+    /// Creates a client that replays `recording` (see `recording`). Each `update` call replays the next recorded frame,
+    /// and the other inputs are ignored. The recorded view size, pixel ratio, language and seed replace the ones of `init`.
+    pub fn replay(mut init: GameClientInit, recording: Box<[u8]>) -> Option<Self> {
+        let replay = match recording::Replay::new(recording, &mut init) {
+            Ok(replay) => replay,
+            Err(e) => {
+                log_err!(e);
+                return None;
+            }
+        };
+
+        init.record_inputs = false;
+        let mut client = Self::initialize(init)?;
+        client.replay = Some(replay);
+
         Some(client)
     }
 
     pub fn update(&mut self, time: f64) {
+        match self.replay.is_some() {
+            true => self.replay_frame(),
+            false => self.input(InputEvent::Update { time }),
+        }
+    }
+
+    pub fn updates_ptr(&self) -> *const output::OutputIndex {
+        self.output.output_index
+    }
+
+    /// `width` and `height` are in css pixels
+    pub fn resize(&mut self, width: u32, height: u32, pixel_ratio: f32) {
+        self.input(InputEvent::Resize { width, height, pixel_ratio });
+    }
+
+    pub fn update_mouse_position(&mut self, x: f32, y: f32) {
+        self.input(InputEvent::MousePosition { x, y });
+    }
+
+    pub fn update_mouse_inside(&mut self, inside: bool) {
+        self.input(InputEvent::MouseInside { inside });
+    }
+
+    pub fn update_mouse_buttons(&mut self, button: u8, pressed: bool) {
+        self.input(InputEvent::MouseButtons { button, pressed });
+    }
+
+    pub fn update_mouse_wheel(&mut self, delta_x: f32, delta_y: f32) {
+        self.input(InputEvent::MouseWheel { delta_x, delta_y });
+    }
+
+    pub fn touch_start(&mut self, id: u32, x: f32, y: f32) {
+        self.input(InputEvent::Touch { id, phase: data::touch::TouchPhase::Start, x, y });
+    }
+
+    pub fn touch_move(&mut self, id: u32, x: f32, y: f32) {
+        self.input(InputEvent::Touch { id, phase: data::touch::TouchPhase::Move, x, y });
+    }
+
+    pub fn touch_end(&mut self, id: u32, x: f32, y: f32) {
+        self.input(InputEvent::Touch { id, phase: data::touch::TouchPhase::End, x, y });
+    }
+
+    pub fn touch_cancel(&mut self, id: u32, x: f32, y: f32) {
+        self.input(InputEvent::Touch { id, phase: data::touch::TouchPhase::Cancel, x, y });
+    }
+
+    pub fn update_keys(&mut self, key_name: &str, pressed: bool, repeat: bool) {
+        self.input(InputEvent::Keys { key_name, pressed, repeat });
+    }
+
+    pub fn update_modifiers(&mut self, alt: bool, ctrl: bool, shift: bool, meta: bool) {
+        self.input(InputEvent::Modifiers { alt, ctrl, shift, meta });
+    }
+
+    /// Text typed by the user. Only used by the gui text fields.
+    pub fn update_text(&mut self, text: &str) {
+        self.input(InputEvent::Text { text });
+    }
+
+    /// IME composition text. `done` is true when the composition is committed.
+    pub fn update_composition(&mut self, text: &str, done: bool) {
+        self.input(InputEvent::Composition { text, done });
+    }
+
+    /// Saves the scene without consuming the client. Snapshots are compressed, see `snapshot_to_text` to keep them in a text storage.
+    pub fn snapshot(&mut self) -> Box<[u8]> {
+        self.as_bytes(true)
+    }
+
+    /// Replaces the scene with a snapshot made by `snapshot`. If the snapshot is invalid, the error is logged,
+    /// the client is unchanged, and this returns false.
+    pub fn restore(&mut self, bytes: &[u8]) -> bool {
+        if self.replay.is_some() {
+            return false;
+        }
+
+        self.record(InputEvent::Restore { bytes });
+        self.restore_snapshot(bytes)
+    }
+
+    /// The inputs recorded since `initialize`. `None` if the client was not initialized with `GameClientInit::record_inputs`.
+    pub fn recording(&self) -> Option<Box<[u8]>> {
+        self.recorder.as_ref()
+            .map(|recorder| Box::from(recorder.data()) )
+    }
+
+    /// True if the client replays a recording and all the recorded frames were replayed
+    pub fn replay_finished(&self) -> bool {
+        self.replay.as_ref().is_some_and(|replay| replay.finished() )
+    }
+
+    /// Number of replay checkpoints checked so far
+    pub fn replay_checkpoints(&self) -> u32 {
+        self.replay.as_ref()
+            .map(|replay| replay.checkpoints )
+            .unwrap_or(0)
+    }
+
+    /// Number of replay checkpoints where the state of the client didn't match the recording
+    pub fn replay_mismatches(&self) -> u32 {
+        self.replay.as_ref()
+            .map(|replay| replay.mismatches )
+            .unwrap_or(0)
+    }
+
+    /// True if `load` failed to restore the saved client. The engine must call `initialize` again.
+    pub fn needs_init(&self) -> bool {
+        self.needs_init
+    }
+
+    /// Returns true if the gui is currently consuming keyboard inputs (ex: a text field has focus)
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.data.gui.wants_keyboard_input()
+    }
+
+}
+
+impl GameClient {
+    pub fn on_reload(&mut self) {
+        self.data.clear_sprites();
+        state::generation::init(self);
+    }
+
+    fn update_frame(&mut self, time: f64) {
         use state::GameStateValue::*;
 
         if self.hidden() {
@@ -101,6 +309,7 @@ impl GameClient {
         }
 
         self.data.prepare_update(time);
+        self.data.stats.begin(data::stats::StatsPhase::StateUpdate);
 
         match self.state.value {
             Uninitialized => state::generation::init(self),
@@ -112,60 +321,197 @@ impl GameClient {
 
         state::handle_gui_events(self);
 
+        self.data.update_move_orders();
+        self.data.stats.end(data::stats::StatsPhase::StateUpdate);
         self.data.finalize_update();
 
         output::GameOutput::update(self);
+
+        let frame_time = self.data.globals.time_delta;
+        self.data.stats.finish_frame(frame_time);
     }
 
-    pub fn updates_ptr(&self) -> *const output::OutputIndex {
-        self.output.output_index
+    fn resize_view(&mut self, width: u32, height: u32, pixel_ratio: f32) {
+        let pixel_ratio = data::sanitize_pixel_ratio(pixel_ratio);
+        let globals = &mut self.data.globals;
+        globals.view_size = shared::size(width as f32, height as f32);
+        globals.pixel_ratio = pixel_ratio;
+        globals.flags.set_update_view_offset();
+        self.data.gui.resize(width, height, pixel_ratio);
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) {
-        self.data.globals.view_size = shared::size(width as f32, height as f32);
-        self.data.gui.resize(width, height);
+    /// Records and applies an input call. Inputs from the engine are ignored when the client replays a recording.
+    fn input(&mut self, input: InputEvent) {
+        if self.replay.is_some() {
+            return;
+        }
+
+        self.record(input);
+        self.apply_input(input);
+
+        if let InputEvent::Update { .. } = input {
+            if self.recorder.as_mut().is_some_and(|recorder| recorder.end_frame() ) {
+                let hash = self.state_hash();
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.checkpoint(hash);
+                }
+            }
+        }
     }
 
-    pub fn update_mouse_position(&mut self, x: f32, y: f32) {
-        self.data.update_mouse_position(x, y);
+    fn record(&mut self, input: InputEvent) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(input);
+        }
     }
 
-    pub fn update_mouse_buttons(&mut self, button: u8, pressed: bool) {
-        self.data.update_mouse_buttons(button, pressed);
+    fn apply_input(&mut self, input: InputEvent) {
+        use data::base::KeyModifiers;
+        use InputEvent::*;
+
+        match input {
+            Update { time } => self.update_frame(time),
+            MousePosition { x, y } => self.data.update_mouse_position(x, y),
+            MouseInside { inside } => self.data.update_mouse_inside(inside),
+            MouseButtons { button, pressed } => self.data.update_mouse_buttons(button, pressed),
+            MouseWheel { delta_x, delta_y } => self.data.update_mouse_wheel(delta_x, delta_y),
+            Touch { id, phase, x, y } => self.data.update_touch(id, phase, x, y),
+            Keys { key_name, pressed, repeat } => self.data.update_keys(key_name, pressed, repeat),
+            Modifiers { alt, ctrl, shift, meta } => self.data.update_modifiers(KeyModifiers::new(alt, ctrl, shift, meta)),
+            Text { text } => self.data.update_text(text),
+            Composition { text, done } => self.data.update_composition(text, done),
+            Resize { width, height, pixel_ratio } => self.resize_view(width, height, pixel_ratio),
+            Restore { bytes } => { self.restore_snapshot(bytes); },
+        }
+    }
+
+    /// Applies the inputs of the next recorded frame and checks the state hash if the recording has one
+    fn replay_frame(&mut self) {
+        let Some(mut replay) = self.replay.take() else {
+            return;
+        };
+
+        replay.next_frame(|input| self.apply_input(input) );
+
+        if let recording::Checkpoint::Mismatched { frame, expected, actual } = replay.checkpoint(|| self.state_hash() ) {
+            warn!("Replay diverged from the recording at frame {}. State hash is {:016x}, expected {:016x}", frame, actual, expected);
+        }
+
+        self.replay = Some(replay);
     }
 
-    pub fn update_keys(&mut self, key_name: &str, pressed: bool) {
-        self.data.gui.update_keys(key_name, pressed);
+    /// Hash of the saved game data and state, used to compare a replay with its recording
+    pub fn state_hash(&mut self) -> u64 {
+        use std::hash::Hasher;
+        let mut hasher = fnv::FnvHasher::default();
+        hasher.write(&self.as_bytes(false));
+        hasher.finish()
     }
 
-}
+    fn restore_snapshot(&mut self, bytes: &[u8]) -> bool {
+        match self.restore_bytes(bytes) {
+            Ok(()) => true,
+            Err(mut e) => {
+                e.merge(save_err!("Failed to restore the snapshot"));
+                log_err!(e);
+                false
+            }
+        }
+    }
 
-impl GameClient {
-    pub fn on_reload(&mut self) {
-        self.data.clear_sprites();
-        state::generation::init(self);
+    /// Top level sections of a save. Loading a save where the schema of one of them changed fails.
+    fn store_sections() -> [store::StoreSection; 7] {
+        use store::StoreSection;
+        [
+            StoreSection::of::<data::GlobalParams>("globals"),
+            StoreSection::of::<data::world::World>("world"),
+            StoreSection::of::<data::terrain::Terrain>("terrain"),
+            StoreSection::of::<data::gui::Gui>("gui"),
+            StoreSection::of::<data::rng::Rng>("rng"),
+            StoreSection::of::<data::actions::KeyBindings>("bindings"),
+            StoreSection::of::<state::GameState>("state"),
+        ]
     }
 
-    pub fn as_bytes(&mut self) -> Box<[u8]> {
-        let mut writer = store::StoreWriter::new();
-        self.data.store(&mut writer);
-        self.state.store(&mut writer);
-        writer.data()
+    /// Saves the client. Assets are not saved, `from_bytes` resolves them again from a `GameClientInit`.
+    pub fn as_bytes(&mut self, compress: bool) -> Box<[u8]> {
+        let mut writer = store::StoreWriter::new(&Self::store_sections());
+        writer.write_section("data", |writer| self.data.store(writer) );
+        writer.write_section("state", |writer| self.state.store(writer) );
+        writer.finish(compress)
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
-        let mut reader = store::StoreReader::new(bytes)?;
+    pub fn from_bytes(bytes: &[u8], init: &GameClientInit) -> Result<Self, Error> {
+        let mut client = Self::read_bytes(bytes)?;
+        client.data.assets.init(init)?;
+        client.init_loaded()?;
+        Ok(client)
+    }
 
-        let mut client = GameClient {
-            data: data::GameData::load(&mut reader)?,
-            state: state::GameState::load(&mut reader)?,
+    /// Reads the saved data and state. The assets are not loaded.
+    fn read_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let content = store::unpack(bytes)?;
+        let mut reader = store::StoreReader::new(&content, &Self::store_sections())?;
+
+        let mut data = None;
+        let mut state = None;
+        reader.read_sections(|reader, tag| {
+            if tag == store::tag("data") {
+                data = Some(data::GameData::load(reader)?);
+            } else if tag == store::tag("state") {
+                state = Some(state::GameState::load(reader)?);
+            } else {
+                return Ok(false);
+            }
+            Ok(true)
+        })?;
+
+        Ok(GameClient {
+            data: data.ok_or_else(|| save_err!("Save doesn't have a game data section") )?,
+            state: state.ok_or_else(|| save_err!("Save doesn't have a game state section") )?,
             output: output::GameOutput::default(),
-        };
+            needs_init: false,
+            recorder: None,
+            replay: None,
+        })
+    }
 
-        client.data.gui.set_state(client.state.value, client.state.input_type);
-        client.data.gui.set_debug_flags(client.data.globals.debug_flags);
+    /// Syncs the gui with a loaded client. The assets must be loaded.
+    fn init_loaded(&mut self) -> Result<(), Error> {
+        self.data.gui.load_font(&self.data.assets)?;
+        self.data.gui.load_style();
 
-        Ok(client)
+        self.data.gui.set_state(self.state.value, self.state.input_type);
+        self.data.gui.set_debug_flags(self.data.globals.debug_flags);
+        self.data.gui.set_follow_selection(self.state.follow_entity.is_some());
+        self.data.gui.set_bindings(self.data.bindings);
+        self.data.gui.set_assets(&self.data.assets);
+
+        Ok(())
+    }
+
+    /// Replaces the scene with a snapshot. The assets, the output buffers, the view and the clock of the current client are kept.
+    pub fn restore_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let mut restored = Self::read_bytes(bytes)?;
+        restored.data.assets = ::std::mem::take(&mut self.data.assets);
+        if let Err(e) = restored.init_loaded() {
+            self.data.assets = restored.data.assets;
+            return Err(e);
+        }
+
+        let globals = &self.data.globals;
+        let (view_size, pixel_ratio) = (globals.view_size, globals.pixel_ratio);
+        restored.data.globals.time = globals.time;
+        restored.data.globals.last_animation_tick = globals.last_animation_tick;
+        restored.data.globals.flags.set_update_terrain();
+        restored.output = ::std::mem::take(&mut self.output);
+        restored.recorder = self.recorder.take();
+        restored.replay = self.replay.take();
+
+        *self = restored;
+        self.resize_view(view_size.width as u32, view_size.height as u32, pixel_ratio);
+
+        Ok(())
     }
 
     pub fn hidden(&mut self) -> bool {
@@ -173,32 +519,52 @@ impl GameClient {
     }
 }
 
+
 /// Export the game client into an array of bytes
 #[wasm_bindgen]
 pub fn save(mut client: GameClient) -> Box<[u8]> {
-    client.as_bytes().to_vec().into_boxed_slice()
+    client.as_bytes(false)
 }
 
-/// Load the game client from an array of bytes
+/// Load the game client from an array of bytes. The assets are resolved from `init`.
 #[wasm_bindgen]
-pub fn load(bytes: Box<[u8]>) -> GameClient {
-    ::std::panic::set_hook(Box::new(logging::panic_handler));
+pub fn load(bytes: Box<[u8]>, init: GameClientInit) -> GameClient {
+    logging::set_panic_hook();
 
-    let client = match GameClient::from_bytes(&bytes) {
+    let client = match GameClient::from_bytes(&bytes, &init) {
         Ok(mut client) => {
             dbg!("Game client reloaded!");
             client.on_reload();
             client
         },
-        Err(e) => {
+        Err(mut e) => {
+            e.merge(save_err!("Failed to restore the saved game client. A new client must be initialized."));
             log_err!(e);
-            GameClient::default()
+            GameClient { needs_init: true, ..GameClient::default() }
         }
     };
 
     client
 }
 
+/// Encodes a snapshot in base64
+#[wasm_bindgen]
+pub fn snapshot_to_text(bytes: &[u8]) -> String {
+    store::base64::encode(bytes)
+}
+
+/// Decodes a snapshot encoded by `snapshot_to_text`. Returns `undefined` if the text is not valid base64.
+#[wasm_bindgen]
+pub fn snapshot_from_text(text: &str) -> Option<Box<[u8]>> {
+    match store::base64::decode(text) {
+        Ok(bytes) => Some(bytes.into_boxed_slice()),
+        Err(e) => {
+            log_err!(e);
+            None
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub fn protocol() -> String {
     output::protocol::compile()
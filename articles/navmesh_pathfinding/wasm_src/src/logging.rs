@@ -1,4 +1,3 @@
-use wasm_bindgen::prelude::*;
 
 
 #[allow(unused_macros)]
@@ -11,24 +10,61 @@ macro_rules! dbg {
 #[allow(unused_macros)]
 macro_rules! warn {
     ($($arg:tt)*) => {
-        crate::logging::log(format!("[WARNING][{}:{}] {}", file!(), line!(), format!($($arg)*)))
+        crate::logging::log_warn(format!("[WARNING][{}:{}] {}", file!(), line!(), format!($($arg)*)))
     };
 }
 
 #[allow(unused_macros)]
 macro_rules! log_err {
     ($err:expr) => {
-        crate::logging::log(format!("{}", $err));
+        crate::logging::log_error(format!("{}", $err));
     };
 }
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console)]
-    pub fn log(s: String);
+#[cfg(target_arch="wasm32")]
+mod console {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = console)]
+        pub fn log(s: String);
+
+        #[wasm_bindgen(js_namespace = console, js_name = warn)]
+        pub fn log_warn(s: String);
+
+        #[wasm_bindgen(js_namespace = console, js_name = error)]
+        pub fn log_error(s: String);
+    }
+}
+
+#[cfg(target_arch="wasm32")]
+pub use console::{log, log_warn, log_error};
+
+/// Native builds (tests and the headless runner) log debug messages to stdout, warnings and errors to stderr
+#[cfg(not(target_arch="wasm32"))]
+pub fn log(s: String) {
+    println!("{}", s);
+}
+
+#[cfg(not(target_arch="wasm32"))]
+pub fn log_warn(s: String) {
+    eprintln!("{}", s);
+}
+
+#[cfg(not(target_arch="wasm32"))]
+pub fn log_error(s: String) {
+    eprintln!("{}", s);
+}
+
+/// Panics are logged to the browser console. Native builds keep the default hook.
+pub fn set_panic_hook() {
+    #[cfg(target_arch="wasm32")]
+    ::std::panic::set_hook(Box::new(panic_handler));
 }
 
-pub fn panic_handler(panic_info: &::std::panic::PanicHookInfo) {
+#[cfg(target_arch="wasm32")]
+fn panic_handler(panic_info: &::std::panic::PanicHookInfo) {
     dbg!("A panic occured!");
 
     if let Some(location) = panic_info.location() {
@@ -0,0 +1,143 @@
+//! Native (non-wasm) helpers used by the tests and the headless runner.
+//! Does what the engine does in the browser: load the files of the assets bundle and pass them to `GameClientInit`.
+use std::path::{Path, PathBuf};
+use crate::error::Error;
+use crate::{GameClient, GameClientInit};
+
+/// Source of the engine assets. The bundle is read from it so the page and the native builds load the same assets.
+const ASSETS_TS: &str = include_str!("../../ts_src/assets.ts");
+
+/// The `ASSETS_BUNDLE` of `ts_src/assets.ts`
+pub fn assets_bundle() -> &'static str {
+    const START: &str = "const ASSETS_BUNDLE = `";
+    let bundle = ASSETS_TS.split_once(START)
+        .and_then(|(_, rest)| rest.split_once('`') )
+        .map(|(bundle, _)| bundle );
+
+    match bundle {
+        Some(bundle) => bundle,
+        None => panic!("`ASSETS_BUNDLE` was not found in ts_src/assets.ts"),
+    }
+}
+
+/// The directory of the demo page (`articles/navmesh_pathfinding`). Bundle paths are relative to it.
+pub fn demo_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+/// Loads the assets of `assets_bundle` from `demo_dir` in `init`.
+/// Shaders are only used by the renderer and are not loaded. Textures are not decoded, only their size is read.
+pub fn load_assets(init: &mut GameClientInit, demo_dir: &Path) -> Result<(), Error> {
+    let mut error: Option<Error> = None;
+
+    let bundle = assets_bundle();
+    init.set_assets_bundle(bundle.to_string());
+
+    crate::shared::split_csv::<5, _>(bundle, |args| {
+        let result = match args {
+            ["TEXTURE", name, path, ..] => {
+                read(demo_dir, path)
+                    .and_then(|png| png_size(&png).ok_or_else(|| assets_err!("{:?} is not a png file", path) ) )
+                    .map(|[width, height]| init.texture_size(name.to_string(), width, height) )
+            },
+            ["CSV", name, path, ..] => {
+                read_text(demo_dir, path)
+                    .map(|csv| init.upload_text_asset(name.to_string(), csv) )
+            },
+            ["STRINGS", language, path, ..] => {
+                read_text(demo_dir, path)
+                    .map(|csv| init.upload_text_asset(format!("strings_{language}"), csv) )
+            },
+            ["FONT", name, path, ..] => {
+                read(demo_dir, path)
+                    .map(|font| init.upload_bin_asset(name.to_string(), font) )
+            },
+            _ => Ok(()),
+        };
+
+        if let Err(new_error) = result {
+            crate::shared::merge_error(&mut error, new_error)
+        }
+    });
+
+    match error {
+        Some(error) => Err(error),
+        None => Ok(())
+    }
+}
+
+/// Readable dump of the output of the last `GameClient::update`, see `output/dump.rs`
+pub fn dump_output(client: &GameClient) -> String {
+    crate::output::dump::dump(&client.output)
+}
+
+/// The random number generator of the game data
+pub fn rng(client: &mut GameClient) -> &mut crate::Rng {
+    &mut client.data.rng
+}
+
+/// Names of the demo states, in the order of the demo
+pub const DEMO_STATES: [&str; 4] = ["generation", "navigation", "obstacles", "final_demo"];
+
+/// Resets the world to the init scene of the demo state `name` (see `DEMO_STATES`) and starts it.
+/// The navigation and obstacles states don't have their own scene and use the generation scene.
+/// If the client records its inputs, the new scene is recorded as a `Restore` so replays start from it.
+/// Returns false if `name` is not a demo state.
+pub fn init_demo_state(client: &mut GameClient, name: &str) -> bool {
+    use crate::state::{self, GameInputType, GameStateValue};
+
+    let value = match name {
+        "generation" => GameStateValue::Generation,
+        "navigation" => GameStateValue::Navigation,
+        "obstacles" => GameStateValue::Obstacles,
+        "final_demo" => GameStateValue::FinalDemo,
+        _ => { return false; }
+    };
+
+    match value {
+        GameStateValue::FinalDemo => state::final_demo::init(client),
+        _ => state::generation::init(client),
+    }
+
+    client.state.value = value;
+    client.data.gui.set_state(value, GameInputType::Select);
+
+    if client.recorder.is_some() {
+        let bytes = client.as_bytes(false);
+        client.record(crate::recording::InputEvent::Restore { bytes: &bytes });
+    }
+
+    true
+}
+
+/// Absolute paths of the bundle are served from the root of the repository, the others from the demo directory
+fn asset_path(demo_dir: &Path, path: &str) -> PathBuf {
+    let path = path.trim();
+    match path.strip_prefix('/') {
+        Some(path) => demo_dir.join("../..").join(path),
+        None => demo_dir.join(path),
+    }
+}
+
+fn read(demo_dir: &Path, path: &str) -> Result<Vec<u8>, Error> {
+    let full_path = asset_path(demo_dir, path);
+    ::std::fs::read(&full_path)
+        .map_err(|e| assets_err!("Failed to read {:?}: {}", full_path, e) )
+}
+
+fn read_text(demo_dir: &Path, path: &str) -> Result<String, Error> {
+    String::from_utf8(read(demo_dir, path)?)
+        .map_err(|_| assets_err!("{:?} is not valid utf-8", path) )
+}
+
+/// Width and height from the IHDR chunk of a png
+fn png_size(data: &[u8]) -> Option<[u32; 2]> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !data.starts_with(SIGNATURE) || data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+
+    let width = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+    Some([width, height])
+}
@@ -6,7 +6,11 @@ use message::*;
 
 pub mod protocol;
 
+#[cfg(not(target_arch="wasm32"))]
+pub mod dump;
+
 use zerocopy::{IntoBytes, Immutable};
+use crate::data::stats::StatsPhase;
 use super::GameClient;
 
 
@@ -31,6 +35,8 @@ pub struct GameOutput {
     data_offset: usize,
     /// Temporary storage for highlighted sprites
     highlighted: Vec<GpuHighlightedSprite>,
+    /// Hash of the gui meshes uploaded to the engine, by slot. Used to only send the meshes that changed.
+    gui_meshes: Vec<u64>,
 }
 
 impl GameOutput {
@@ -48,12 +54,13 @@ impl GameOutput {
     }
 
     pub fn update(client: &mut GameClient) {
+        client.data.stats.begin(StatsPhase::Output);
         let mut flags = client.data.globals.flags;
 
         client.output.clear_index();
 
         if flags.update_view_offset() {
-            GameOutput::update_view_offset(client);
+            GameOutput::update_view_transform(client);
             flags.clear_update_view_offset();
         }
 
@@ -82,6 +89,10 @@ impl GameOutput {
 
         client.data.globals.flags = flags;
         client.output.write_index();
+
+        let output = &client.output;
+        client.data.stats.set_output(output.messages.len(), output.data_offset);
+        client.data.stats.end(StatsPhase::Output);
     }
 
     fn render_sprites(client: &mut GameClient) {
@@ -90,7 +101,9 @@ impl GameOutput {
         let texture_id = client.data.assets.atlas.texture.id;
         let output = &mut client.output;
 
+        client.data.stats.begin(StatsPhase::SpriteOrdering);
         let instance_count = client.data.world.order_sprites(flags.update_animations());
+        client.data.stats.end(StatsPhase::SpriteOrdering);
         
         let update_sprites = UpdateSpritesParams { 
             offset_bytes: output.data_offset,
@@ -114,11 +127,17 @@ impl GameOutput {
         for sprite in client.data.world.ordered_sprites() {
             let [width, height] = sprite.texcoord.splat_size();
 
+            // Flipped sprites are sampled from right to left
+            let (texcoord_left, texcoord_width) = match sprite.flags.flipped() {
+                true => (sprite.texcoord.right, -width),
+                false => (sprite.texcoord.left, width),
+            };
+
             let gpu_sprite = GpuSpriteData {
                 position: sprite.position.splat(),
                 size: [width, height],
-                texcoord_offset: [sprite.texcoord.left, sprite.texcoord.top],
-                texcoord_size: [width, height],
+                texcoord_offset: [texcoord_left, sprite.texcoord.top],
+                texcoord_size: [texcoord_width, height],
             };
             output.push_data(&gpu_sprite);
 
@@ -193,7 +212,7 @@ impl GameOutput {
     }
 
     fn update_terrain(client: &mut GameClient) {
-        const TERRAIN_SPRITE_SIZE: f32 = 64.0;
+        use crate::data::terrain::TERRAIN_CELL_SIZE;
 
         let data = &client.data;
         let output = &mut client.output;
@@ -220,11 +239,11 @@ impl GameOutput {
                 sprite.position = [x, y];
                 sprite.uv = [0.0, 0.0];
                 output.push_data(&sprite);
-                x += TERRAIN_SPRITE_SIZE;
+                x += TERRAIN_CELL_SIZE;
             }
 
             x = 0.0;
-            y += TERRAIN_SPRITE_SIZE;
+            y += TERRAIN_CELL_SIZE;
         }
     }
 
@@ -271,14 +290,20 @@ impl GameOutput {
             // Upload data
             let pixels_offset;
             let pixels_size;
+            let format;
             let [x, y] = delta.pos.unwrap_or([0, 0]);
             let [width, height] = delta.image.size();
             match &delta.image {
-                egui::ImageData::Color(_image) => { panic!("Unsupported"); },
+                egui::ImageData::Color(image) => {
+                    pixels_size = image.pixels.len() * size_of::<egui::Color32>();
+                    pixels_offset = self.push_bytes(&image.pixels);
+                    format = GuiTextureFormat::Rgba8;
+                },
                 egui::ImageData::Font(image) => {
-                    let data: Vec<u8> = image.srgba_pixels(None).flat_map(|a| a.to_array() ).collect();
+                    let data: Vec<u8> = image.srgba_pixels(None).map(|color| color.a() ).collect();
                     pixels_size = data.len();
                     pixels_offset = self.push_bytes(&data);
+                    format = GuiTextureFormat::Alpha8;
                 }
             }
 
@@ -290,10 +315,8 @@ impl GameOutput {
                 y: y as u32,
                 width: width as u32,
                 height: height as u32,
-                id: match id {
-                    egui::TextureId::Managed(x) => *x as u32,
-                    egui::TextureId::User(x) => *x as u32,
-                }
+                id: gui_texture_id(*id),
+                format,
             };
 
             self.messages.push(OutputMessage { 
@@ -304,10 +327,21 @@ impl GameOutput {
     }
 
     #[cfg(feature="gui")]
-    fn update_gui_mesh(&mut self, mesh: &Vec<egui::ClippedPrimitive>) {
+    fn free_gui_textures(&mut self, delta: &egui::TexturesDelta) {
+        for id in delta.free.iter() {
+            let gui_texture_free = GuiTextureFreeParams { id: gui_texture_id(*id) };
+            self.messages.push(OutputMessage { 
+                ty: OutputMessageType::GuiTextureFree,
+                params: OutputMessageParams { gui_texture_free } }
+            );
+        }
+    }
+
+    #[cfg(feature="gui")]
+    fn update_gui_mesh(&mut self, mesh: &[egui::ClippedPrimitive]) {
         use egui::epaint::{Primitive, Vertex, Rect};
 
-        fn update_mesh(clip: &Rect, mesh: &egui::Mesh, output: &mut GameOutput) {
+        fn update_mesh(slot: u32, clip: &Rect, mesh: &egui::Mesh, output: &mut GameOutput) {
             let index_offset_bytes = output.push_bytes(&mesh.indices);
             let vertex_offset_bytes = output.push_bytes(&mesh.vertices);
             let index_size_bytes = mesh.indices.len() * size_of::<u32>();
@@ -319,10 +353,8 @@ impl GameOutput {
                 vertex_size_bytes,
                 count: mesh.indices.len() as u32,
                 clip: [clip.min.x, clip.min.y, clip.max.x, clip.max.y],
-                texture_id: match mesh.texture_id {
-                    egui::TextureId::Managed(x) => x as u32,
-                    egui::TextureId::User(x) => x as u32,
-                }
+                texture_id: gui_texture_id(mesh.texture_id),
+                slot,
             };
 
             output.messages.push(OutputMessage { 
@@ -331,36 +363,90 @@ impl GameOutput {
             );
         }
 
+        fn mesh_hash(clip: &Rect, mesh: &egui::Mesh) -> u64 {
+            use std::hash::Hasher;
+            let mut hasher = fnv::FnvHasher::default();
+            for value in [clip.min.x, clip.min.y, clip.max.x, clip.max.y] {
+                hasher.write_u32(value.to_bits());
+            }
+            hasher.write_u32(gui_texture_id(mesh.texture_id));
+            hasher.write(mesh.indices.as_bytes());
+            for vertex in mesh.vertices.iter() {
+                for value in [vertex.pos.x, vertex.pos.y, vertex.uv.x, vertex.uv.y] {
+                    hasher.write_u32(value.to_bits());
+                }
+                hasher.write(&vertex.color.to_array());
+            }
+            hasher.finish()
+        }
+
+        let mut slot = 0;
         for clipped_primitive in mesh.iter() {
             match &clipped_primitive.primitive {
-                Primitive::Callback(_) => { panic!("Unsupported") },
+                // The gui never adds paint callbacks
+                Primitive::Callback(_) => {},
                 Primitive::Mesh(mesh) => {
-                    update_mesh(&clipped_primitive.clip_rect, mesh, self);
+                    let clip = &clipped_primitive.clip_rect;
+                    let hash = mesh_hash(clip, mesh);
+                    match self.gui_meshes.get_mut(slot) {
+                        Some(old_hash) if *old_hash == hash => {},
+                        Some(old_hash) => {
+                            *old_hash = hash;
+                            update_mesh(slot as u32, clip, mesh, self);
+                        },
+                        None => {
+                            self.gui_meshes.push(hash);
+                            update_mesh(slot as u32, clip, mesh, self);
+                        }
+                    }
+
+                    slot += 1;
                 }
             }
         }
+
+        self.gui_meshes.truncate(slot);
+
+        let gui_mesh_count = GuiMeshCountParams { count: slot as u32 };
+        self.messages.push(OutputMessage { 
+            ty: OutputMessageType::GuiMeshCount,
+            params: OutputMessageParams { gui_mesh_count } }
+        );
     }
     
     #[cfg(feature="gui")]
     fn render_gui(client: &mut GameClient) {
         let output = &mut client.output;
 
-        output.messages.push(OutputMessage { ty: OutputMessageType::ResetGui, params: OutputMessageParams { none: () } });
-
         let delta = client.data.gui.texture_delta();
         output.update_gui_textures(&delta);
 
+        client.data.stats.begin(StatsPhase::GuiTessellation);
         let mesh = client.data.gui.tesselate();
-        output.update_gui_mesh(&mesh);
+        client.data.stats.end(StatsPhase::GuiTessellation);
+
+        // `None` if the gui shapes didn't change, in this case the engine meshes are still valid
+        if let Some(mesh) = mesh {
+            output.update_gui_mesh(&mesh);
+        }
+
+        output.free_gui_textures(&delta);
     }
 
     #[cfg(not(feature="gui"))]
     fn render_gui(_client: &mut GameClient) {}
 
-    fn update_view_offset(client: &mut GameClient) {
+    fn update_view_transform(client: &mut GameClient) {
+        let globals = &client.data.globals;
+        let update_view_transform = UpdateViewTransformParams {
+            offset: globals.view_offset.splat(),
+            scale: globals.view_scale,
+            pixel_ratio: globals.pixel_ratio,
+        };
+
         client.output.messages.push(OutputMessage { 
-            ty: OutputMessageType::UpdateViewOffset,
-            params: OutputMessageParams { update_view_offset: client.data.globals.view_offset },
+            ty: OutputMessageType::UpdateViewTransform,
+            params: OutputMessageParams { update_view_transform },
         });
     }
 
@@ -370,7 +456,7 @@ impl GameOutput {
             Self::realloc_data(&mut self.data, size);
         }
 
-        if let Err(_) = data.write_to_prefix(&mut self.data[self.data_offset..]) {
+        if data.write_to_prefix(&mut self.data[self.data_offset..]).is_err() {
             unsafe { std::hint::unreachable_unchecked() } // Safety. Capacity check above ensure this this never be reached
         }
 
@@ -417,7 +503,8 @@ impl Default for GameOutput {
             messages: Vec::with_capacity(16),
             data: vec![0; 0xF0000],
             data_offset: 0,
-            highlighted: Vec::with_capacity(16)
+            highlighted: Vec::with_capacity(16),
+            gui_meshes: Vec::new(),
         }
     }
 
@@ -434,3 +521,11 @@ impl Default for OutputIndex {
     }
 }
 
+
+#[cfg(feature="gui")]
+fn gui_texture_id(id: egui::TextureId) -> u32 {
+    match id {
+        egui::TextureId::Managed(x) => x as u32,
+        egui::TextureId::User(x) => (x as u32) | GUI_USER_TEXTURE,
+    }
+}
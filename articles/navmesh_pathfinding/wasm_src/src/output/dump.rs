@@ -0,0 +1,110 @@
+//! Readable dump of the output of a frame, read through the `OutputIndex` like the engine does.
+//! Only used by native builds (headless runner and tests).
+//!
+//! Sprites, terrain cells and vertices are decoded from the data buffer, one per line. Gui textures, gui meshes
+//! and debug meshes are too large to be read, so only their size and a hash of their bytes are written.
+use std::fmt::{Debug, Write};
+use zerocopy::FromBytes;
+use super::*;
+
+pub fn dump(output: &GameOutput) -> String {
+    let index = &output.output_index;
+    let mut text = String::with_capacity(1024);
+    let _ = writeln!(text, "OutputIndex {{ messages_count: {}, messages_size: {} }}", index.messages_count, index.messages_size);
+    if index.messages_count == 0 {
+        return text;
+    }
+
+    // Safety: the index always points to the messages and the data of the last `GameOutput::update`, which are still borrowed here
+    let messages = unsafe { ::std::slice::from_raw_parts(index.messages_ptr, index.messages_count) };
+    let data = unsafe { ::std::slice::from_raw_parts(index.data_ptr, output.data_offset) };
+    for message in messages {
+        dump_message(&mut text, message, data);
+    }
+
+    text
+}
+
+fn dump_message(text: &mut String, message: &OutputMessage, data: &[u8]) {
+    use OutputMessageType::*;
+
+    // Safety: `ty` is always the type of the union field written in `params`
+    let params = &message.params;
+    let _ = unsafe { match message.ty {
+        UpdateSprites => {
+            let params = params.update_sprites;
+            writeln!(text, "UpdateSprites {:?}", params)
+                .map(|_| dump_values::<GpuSpriteData>(text, data, params.offset_bytes, params.size_bytes) )
+        },
+        DrawSprites => writeln!(text, "DrawSprites {:?}", params.draw_sprites),
+        UpdateHighlightSprites => {
+            let params = params.update_highlight_sprites;
+            writeln!(text, "UpdateHighlightSprites {:?}", params)
+                .map(|_| dump_values::<GpuHighlightedSprite>(text, data, params.offset_bytes, params.size_bytes) )
+        },
+        HighlightSprites => writeln!(text, "HighlightSprites {:?}", params.highlight_sprites),
+        UpdateTerrain => {
+            let params = params.update_terrain;
+            writeln!(text, "UpdateTerrain {:?}", params)
+                .map(|_| dump_values::<GpuTerrainSpriteData>(text, data, params.offset_bytes, params.size_bytes) )
+        },
+        DrawDebug => {
+            let params = params.draw_debug;
+            writeln!(text, "DrawDebug {:?}", params)
+                .map(|_| dump_hash(text, "indices", data, params.index_offset_bytes, params.index_size_bytes) )
+                .map(|_| dump_hash(text, "vertices", data, params.vertex_offset_bytes, params.vertex_size_bytes) )
+        },
+        GuiTextureUpdate => {
+            let params = params.gui_texture_update;
+            writeln!(text, "GuiTextureUpdate {:?}", params)
+                .map(|_| dump_hash(text, "pixels", data, params.pixels_offset, params.pixels_size) )
+        },
+        GuiTextureFree => writeln!(text, "GuiTextureFree {:?}", params.gui_texture_free),
+        GuiMeshUpdate => {
+            let params = params.gui_mesh_update;
+            writeln!(text, "GuiMeshUpdate {:?}", params)
+                .map(|_| dump_hash(text, "indices", data, params.index_offset_bytes, params.index_size_bytes) )
+                .map(|_| dump_hash(text, "vertices", data, params.vertex_offset_bytes, params.vertex_size_bytes) )
+        },
+        GuiMeshCount => writeln!(text, "GuiMeshCount {:?}", params.gui_mesh_count),
+        UpdateViewTransform => writeln!(text, "UpdateViewTransform {:?}", params.update_view_transform),
+        DrawInsertSprite => {
+            let params = params.draw_insert_sprite;
+            writeln!(text, "DrawInsertSprite {:?}", params)
+                .map(|_| dump_values::<InsertSpriteVertex>(text, data, params.vertex_offset_bytes, params.vertex_size_bytes) )
+        },
+    }};
+}
+
+/// The bytes at `offset` of the data buffer, or `None` if a message points outside the data written this frame
+fn data_range(data: &[u8], offset: usize, size: usize) -> Option<&[u8]> {
+    offset.checked_add(size).and_then(|end| data.get(offset..end) )
+}
+
+fn dump_values<T: FromBytes + Debug>(text: &mut String, data: &[u8], offset: usize, size: usize) {
+    let Some(bytes) = data_range(data, offset, size) else {
+        let _ = writeln!(text, "  INVALID RANGE {}..{} (data size: {})", offset, offset.saturating_add(size), data.len());
+        return;
+    };
+
+    // Values in the data buffer are not aligned
+    for chunk in bytes.chunks(size_of::<T>()) {
+        match T::read_from_bytes(chunk) {
+            Ok(value) => { let _ = writeln!(text, "  {:?}", value); },
+            Err(_) => { let _ = writeln!(text, "  INCOMPLETE VALUE ({} bytes)", chunk.len()); },
+        }
+    }
+}
+
+fn dump_hash(text: &mut String, name: &str, data: &[u8], offset: usize, size: usize) {
+    use std::hash::Hasher;
+
+    let Some(bytes) = data_range(data, offset, size) else {
+        let _ = writeln!(text, "  {}: INVALID RANGE {}..{} (data size: {})", name, offset, offset.saturating_add(size), data.len());
+        return;
+    };
+
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(bytes);
+    let _ = writeln!(text, "  {}: {} bytes, hash {:016x}", name, bytes.len(), hasher.finish());
+}
@@ -4,7 +4,7 @@ use zerocopy_derive::*;
 /// Information on how to render a sprite on the GPU
 /// Memory layout must match `in_instance_position`, `in_instance_texcoord`, `in_instance_data` in `sprites.vert.glsl`
 #[repr(C)]
-#[derive(Copy, Clone, Immutable, IntoBytes)]
+#[derive(Copy, Clone, Debug, Immutable, IntoBytes, FromBytes)]
 pub struct GpuSpriteData {
     pub position: [f32; 2],
     pub size: [f32; 2],
@@ -13,28 +13,28 @@ pub struct GpuSpriteData {
 }
 
 #[repr(C)]
-#[derive(Default, Copy, Clone, Immutable, IntoBytes)]
+#[derive(Default, Copy, Clone, Debug, Immutable, IntoBytes, FromBytes)]
 pub struct GpuTerrainSpriteData {
     pub position: [f32; 2],
     pub uv: [f32; 2]
 }
 
 #[repr(C)]
-#[derive(Default, Copy, Clone, Immutable, IntoBytes)]
+#[derive(Default, Copy, Clone, Debug, Immutable, IntoBytes, FromBytes)]
 pub struct GpuDebugVertex {
     pub position: [f32; 2],
     pub color: [u8; 4]
 }
 
 #[repr(C)]
-#[derive(Default, Copy, Clone, Immutable, IntoBytes)]
+#[derive(Default, Copy, Clone, Debug, Immutable, IntoBytes, FromBytes)]
 pub struct InsertSpriteVertex {
     pub position: [f32; 2],
     pub texcoord: [f32; 2]
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Immutable, IntoBytes)]
+#[derive(Copy, Clone, Debug, Immutable, IntoBytes, FromBytes)]
 pub struct GpuHighlightedSprite {
     pub position: [f32; 2],
     pub size: [f32; 2],
@@ -1,8 +1,6 @@
-use crate::shared::PositionF32;
-
 /// Engine must read data in the client data buffer and copy it in the engine sprite instance buffer
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct UpdateSpritesParams {
     /// The offset in the client data buffer
     pub offset_bytes: usize,
@@ -13,7 +11,7 @@ pub struct UpdateSpritesParams {
 /// Engine must draw `instance_count` sprites using the "Sprites" shader, starting at `instance_base` and using `texture_id`
 /// Data comes from the `UpdateSprites` command
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct DrawSpritesParams {
     pub instance_base: u32,
     pub instance_count: u32,
@@ -21,7 +19,7 @@ pub struct DrawSpritesParams {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct UpdateTerrainParams {
     pub offset_bytes: usize,
     pub size_bytes: usize,
@@ -29,7 +27,7 @@ pub struct UpdateTerrainParams {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct DrawDebugParams {
     pub index_offset_bytes: usize,
     pub index_size_bytes: usize,
@@ -38,8 +36,22 @@ pub struct DrawDebugParams {
     pub count: usize,
 }
 
+/// Bit set on the gui texture ids referencing an engine texture (ex: the sprites atlas) instead of a texture uploaded by `GuiTextureUpdate`.
+/// The remaining bits are the asset texture id.
+pub const GUI_USER_TEXTURE: u32 = 0x8000_0000;
+
+/// Layout of the pixels of a `GuiTextureUpdate`
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum GuiTextureFormat {
+    /// Premultiplied sRGBA, 4 bytes per pixel. Used by color images.
+    Rgba8,
+    /// Coverage, 1 byte per pixel. Used by the font atlas. Engine must expand it to premultiplied white (`a, a, a, a`).
+    Alpha8,
+}
+
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct GuiTextureUpdateParams {
     pub pixels_offset: usize,
     pub pixels_size: usize,
@@ -48,10 +60,18 @@ pub struct GuiTextureUpdateParams {
     pub width: u32,
     pub height: u32,
     pub id: u32,
+    pub format: GuiTextureFormat,
 }
 
+/// Engine must delete the gui texture `id`. Sent after the meshes of the frame.
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
+pub struct GuiTextureFreeParams {
+    pub id: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
 pub struct GuiMeshUpdateParams {
     pub index_offset_bytes: usize,
     pub index_size_bytes: usize,
@@ -60,10 +80,30 @@ pub struct GuiMeshUpdateParams {
     pub clip: [f32; 4],
     pub count: u32,
     pub texture_id: u32,
+    /// Index of the mesh to replace in the engine gui meshes list
+    pub slot: u32,
 }
 
+/// Engine must keep the first `count` gui meshes and drop the others.
+/// Meshes that didn't change since the last frame are not sent again by `GuiMeshUpdate`.
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
+pub struct GuiMeshCountParams {
+    pub count: u32,
+}
+
+/// Engine must translate the world by `offset` and then scale it by `scale` when rendering world elements
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct UpdateViewTransformParams {
+    pub offset: [f32; 2],
+    pub scale: f32,
+    /// Physical pixels per css pixel. The world and the gui must both be scaled by this value.
+    pub pixel_ratio: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
 pub struct DrawInsertSpriteParams {
     pub vertex_offset_bytes: usize,
     pub vertex_size_bytes: usize,
@@ -81,13 +121,15 @@ pub union OutputMessageParams {
     pub update_terrain: UpdateTerrainParams,
     pub draw_debug: DrawDebugParams,
     pub gui_texture_update: GuiTextureUpdateParams,
+    pub gui_texture_free: GuiTextureFreeParams,
     pub gui_mesh_update: GuiMeshUpdateParams,
-    pub update_view_offset: PositionF32,
+    pub gui_mesh_count: GuiMeshCountParams,
+    pub update_view_transform: UpdateViewTransformParams,
     pub draw_insert_sprite: DrawInsertSpriteParams,
 }
 
 #[repr(u32)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum OutputMessageType {
     UpdateSprites,
     DrawSprites,
@@ -96,9 +138,10 @@ pub enum OutputMessageType {
     UpdateTerrain,
     DrawDebug,
     GuiTextureUpdate,
+    GuiTextureFree,
     GuiMeshUpdate,
-    ResetGui,
-    UpdateViewOffset,
+    GuiMeshCount,
+    UpdateViewTransform,
     DrawInsertSprite,
 }
 
@@ -114,8 +157,14 @@ pub struct OutputMessage {
 // Other impl
 //
 
-impl Into<u32> for OutputMessageType {
-    fn into(self) -> u32 {
-        self as u32
+impl From<OutputMessageType> for u32 {
+    fn from(value: OutputMessageType) -> u32 {
+        value as u32
+    }
+}
+
+impl From<GuiTextureFormat> for u32 {
+    fn from(value: GuiTextureFormat) -> u32 {
+        value as u32
     }
 }
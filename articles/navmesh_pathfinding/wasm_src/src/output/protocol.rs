@@ -12,12 +12,12 @@ fn generate_enum<T: Copy + Into<u32>>(
     name: &str,
     fields: &[(&str, T)]
 ) {
-    write!(out, "const {name} = Object.freeze({{\n").unwrap();
+    writeln!(out, "const {name} = Object.freeze({{").unwrap();
     for (name, value) in fields.iter() {
         let raw_value: u32 = (*value).into();
-        write!(out, "  {raw_value}: \'{name}\',\n").unwrap();
+        writeln!(out, "  {raw_value}: \'{name}\',").unwrap();
     }
-    write!(out, "}})\n").unwrap();
+    writeln!(out, "}})").unwrap();
 }
 
 /// A struct that contain only primitive
@@ -27,7 +27,7 @@ fn generate_struct(
     size: usize,
     fields: &[(&str, &str, usize)],
 ) {
-    write!(out, "export class {name} {{\n").unwrap();
+    writeln!(out, "export class {name} {{").unwrap();
 
     write!(out, r#"
         constructor(buffer, ptr) {{
@@ -53,7 +53,7 @@ fn generate_struct_with_custom_fields(
     fields: &[(&str, &str, usize)],
     custom_fields: &[(&str, &str)]
 ) {
-    write!(out, "export class {name} {{\n").unwrap();
+    writeln!(out, "export class {name} {{").unwrap();
 
     write!(out, r#"
         constructor(buffer, ptr) {{
@@ -84,7 +84,7 @@ fn get_array_function(accessor: &str, base_offset: usize, item_size: usize, item
     let mut out = String::with_capacity(32);
     out.push_str("return [");
     for i in 0..item_count {
-        let offset = base_offset + ((i * item_size) as usize);
+        let offset = base_offset + (i * item_size);
         out.push_str(&format!("this.view.{accessor}({offset}, true),"))
     }
     out.push_str("];");
@@ -112,13 +112,23 @@ pub fn compile() -> String {
             ("UpdateTerrain", OutputMessageType::UpdateTerrain),
             ("DrawDebug", OutputMessageType::DrawDebug),
             ("GuiTextureUpdate", OutputMessageType::GuiTextureUpdate),
+            ("GuiTextureFree", OutputMessageType::GuiTextureFree),
             ("GuiMeshUpdate", OutputMessageType::GuiMeshUpdate),
-            ("ResetGui", OutputMessageType::ResetGui),
-            ("UpdateViewOffset", OutputMessageType::UpdateViewOffset),
+            ("GuiMeshCount", OutputMessageType::GuiMeshCount),
+            ("UpdateViewTransform", OutputMessageType::UpdateViewTransform),
             ("DrawInsertSprite", OutputMessageType::DrawInsertSprite),
         ]
     );
 
+    generate_enum(
+        &mut source,
+        "GuiTextureFormat",
+        &[
+            ("Rgba8", GuiTextureFormat::Rgba8),
+            ("Alpha8", GuiTextureFormat::Alpha8),
+        ]
+    );
+
     generate_struct(
         &mut source, 
         "OutputIndex", 
@@ -176,7 +186,7 @@ pub fn compile() -> String {
         ],
     );
 
-    generate_struct(
+    generate_struct_with_custom_fields(
         &mut source, 
         "GuiTextureUpdateParams", 
         size_of::<GuiTextureUpdateParams>(),
@@ -189,6 +199,18 @@ pub fn compile() -> String {
             ("height", "getUint32", offset_of!(GuiTextureUpdateParams, height)),
             ("id", "getUint32", offset_of!(GuiTextureUpdateParams, id)),
         ],
+        &[
+            ("format", &format!("return GuiTextureFormat[this.view.getUint32({}, true)];", offset_of!(GuiTextureUpdateParams, format)))
+        ]
+    );
+
+    generate_struct(
+        &mut source, 
+        "GuiTextureFreeParams", 
+        size_of::<GuiTextureFreeParams>(),
+        &[
+            ("id", "getUint32", offset_of!(GuiTextureFreeParams, id)),
+        ],
     );
 
     generate_struct_with_custom_fields(
@@ -202,12 +224,35 @@ pub fn compile() -> String {
             ("vertex_size_bytes", pointer_type, offset_of!(GuiMeshUpdateParams, vertex_size_bytes)),
             ("count", "getUint32", offset_of!(GuiMeshUpdateParams, count)),
             ("texture_id", "getUint32", offset_of!(GuiMeshUpdateParams, texture_id)),
+            ("slot", "getUint32", offset_of!(GuiMeshUpdateParams, slot)),
         ],
         &[
             ("clip", &get_array_function("getFloat32", offset_of!(GuiMeshUpdateParams, clip), size_of::<f32>(), 4))
         ]
     );
 
+    generate_struct(
+        &mut source, 
+        "GuiMeshCountParams", 
+        size_of::<GuiMeshCountParams>(),
+        &[
+            ("count", "getUint32", offset_of!(GuiMeshCountParams, count)),
+        ],
+    );
+
+    generate_struct_with_custom_fields(
+        &mut source, 
+        "UpdateViewTransformParams", 
+        size_of::<UpdateViewTransformParams>(),
+        &[
+            ("scale", "getFloat32", offset_of!(UpdateViewTransformParams, scale)),
+            ("pixel_ratio", "getFloat32", offset_of!(UpdateViewTransformParams, pixel_ratio)),
+        ],
+        &[
+            ("offset", &get_array_function("getFloat32", offset_of!(UpdateViewTransformParams, offset), size_of::<f32>(), 2))
+        ]
+    );
+
     generate_struct(
         &mut source, 
         "DrawInsertSpriteParams", 
@@ -235,8 +280,10 @@ pub fn compile() -> String {
             ("update_terrain", "return new UpdateTerrainParams(this.view.buffer, this.view.byteOffset + 4);"),
             ("draw_debug", "return new DrawDebugParams(this.view.buffer, this.view.byteOffset + 4);"),
             ("gui_texture_update", "return new GuiTextureUpdateParams(this.view.buffer, this.view.byteOffset + 4);"),
+            ("gui_texture_free", "return new GuiTextureFreeParams(this.view.buffer, this.view.byteOffset + 4);"),
             ("gui_mesh_update", "return new GuiMeshUpdateParams(this.view.buffer, this.view.byteOffset + 4);"),
-            ("update_view_offset", "return [this.view.getFloat32(4, true), this.view.getFloat32(8, true)];"),
+            ("gui_mesh_count", "return new GuiMeshCountParams(this.view.buffer, this.view.byteOffset + 4);"),
+            ("update_view_transform", "return new UpdateViewTransformParams(this.view.buffer, this.view.byteOffset + 4);"),
         ]
     );
 
@@ -0,0 +1,371 @@
+//! Recording of the inputs of a `GameClient` and deterministic replay.
+//!
+//! A recording starts with the parameters of `GameClientInit` (without the assets), followed by every input call in order.
+//! `update` calls store the time of the frame, the other inputs are applied before the update that follows them.
+//! Every `CHECKPOINT_INTERVAL` frames, a hash of the game data and state is stored so the replay can detect divergences.
+//!
+//! Values are little endian and unaligned. Strings and byte arrays are prefixed by their length (u32).
+use crate::data::touch::TouchPhase;
+use crate::error::Error;
+use crate::GameClientInit;
+
+/// First 4 bytes of every recording
+const RECORDING_MAGIC: &[u8; 4] = b"NMPR";
+
+/// Must be incremented when the encoding of the events change
+const RECORDING_VERSION: u32 = 1;
+
+/// Number of frames between two state hashes
+pub const CHECKPOINT_INTERVAL: u32 = 30;
+
+/// An input call of `GameClient`
+#[derive(Copy, Clone, PartialEq)]
+pub enum InputEvent<'a> {
+    Update { time: f64 },
+    MousePosition { x: f32, y: f32 },
+    MouseInside { inside: bool },
+    MouseButtons { button: u8, pressed: bool },
+    MouseWheel { delta_x: f32, delta_y: f32 },
+    Touch { id: u32, phase: TouchPhase, x: f32, y: f32 },
+    Keys { key_name: &'a str, pressed: bool, repeat: bool },
+    Modifiers { alt: bool, ctrl: bool, shift: bool, meta: bool },
+    Text { text: &'a str },
+    Composition { text: &'a str, done: bool },
+    Resize { width: u32, height: u32, pixel_ratio: f32 },
+    Restore { bytes: &'a [u8] },
+}
+
+/// An entry of the recording
+#[derive(Copy, Clone, PartialEq)]
+enum Event<'a> {
+    Input(InputEvent<'a>),
+    /// Hash of the game data and state after the last update
+    Checkpoint { hash: u64 },
+}
+
+// Event ids in the recording. Must never change.
+const UPDATE: u8 = 0;
+const MOUSE_POSITION: u8 = 1;
+const MOUSE_INSIDE: u8 = 2;
+const MOUSE_BUTTONS: u8 = 3;
+const MOUSE_WHEEL: u8 = 4;
+const TOUCH: u8 = 5;
+const KEYS: u8 = 6;
+const MODIFIERS: u8 = 7;
+const TEXT: u8 = 8;
+const COMPOSITION: u8 = 9;
+const RESIZE: u8 = 10;
+const RESTORE: u8 = 11;
+const CHECKPOINT: u8 = 12;
+
+/// Records the inputs of a client, see `GameClientInit::record_inputs`
+pub struct Recorder {
+    data: Vec<u8>,
+    frame: u32,
+}
+
+impl Recorder {
+
+    pub fn new(init: &GameClientInit) -> Self {
+        let mut recorder = Recorder {
+            data: Vec::with_capacity(0x10000),
+            frame: 0,
+        };
+
+        recorder.data.extend_from_slice(RECORDING_MAGIC);
+        recorder.write_u32(RECORDING_VERSION);
+        recorder.write_f32(init.view_size.width);
+        recorder.write_f32(init.view_size.height);
+        recorder.write_f32(init.pixel_ratio);
+        recorder.write_u32(init.max_texture_size);
+        recorder.write_u32(init.seed);
+        recorder.write_bytes(init.language.as_bytes());
+
+        recorder
+    }
+
+    pub fn record(&mut self, input: InputEvent) {
+        use InputEvent::*;
+
+        match input {
+            Update { time } => {
+                self.data.push(UPDATE);
+                self.data.extend_from_slice(&time.to_le_bytes());
+            },
+            MousePosition { x, y } => {
+                self.data.push(MOUSE_POSITION);
+                self.write_f32(x);
+                self.write_f32(y);
+            },
+            MouseInside { inside } => {
+                self.data.extend_from_slice(&[MOUSE_INSIDE, inside as u8]);
+            },
+            MouseButtons { button, pressed } => {
+                self.data.extend_from_slice(&[MOUSE_BUTTONS, button, pressed as u8]);
+            },
+            MouseWheel { delta_x, delta_y } => {
+                self.data.push(MOUSE_WHEEL);
+                self.write_f32(delta_x);
+                self.write_f32(delta_y);
+            },
+            Touch { id, phase, x, y } => {
+                self.data.extend_from_slice(&[TOUCH, phase as u8]);
+                self.write_u32(id);
+                self.write_f32(x);
+                self.write_f32(y);
+            },
+            Keys { key_name, pressed, repeat } => {
+                self.data.extend_from_slice(&[KEYS, pressed as u8, repeat as u8]);
+                self.write_bytes(key_name.as_bytes());
+            },
+            Modifiers { alt, ctrl, shift, meta } => {
+                let bits = (alt as u8) | ((ctrl as u8) << 1) | ((shift as u8) << 2) | ((meta as u8) << 3);
+                self.data.extend_from_slice(&[MODIFIERS, bits]);
+            },
+            Text { text } => {
+                self.data.push(TEXT);
+                self.write_bytes(text.as_bytes());
+            },
+            Composition { text, done } => {
+                self.data.extend_from_slice(&[COMPOSITION, done as u8]);
+                self.write_bytes(text.as_bytes());
+            },
+            Resize { width, height, pixel_ratio } => {
+                self.data.push(RESIZE);
+                self.write_u32(width);
+                self.write_u32(height);
+                self.write_f32(pixel_ratio);
+            },
+            Restore { bytes } => {
+                self.data.push(RESTORE);
+                self.write_bytes(bytes);
+            }
+        }
+    }
+
+    /// Called after each update. Returns true if the state hash must be recorded with `checkpoint`.
+    pub fn end_frame(&mut self) -> bool {
+        let checkpoint = self.frame.is_multiple_of(CHECKPOINT_INTERVAL);
+        self.frame += 1;
+        checkpoint
+    }
+
+    pub fn checkpoint(&mut self, hash: u64) {
+        self.data.push(CHECKPOINT);
+        self.data.extend_from_slice(&hash.to_le_bytes());
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.data.extend_from_slice(bytes);
+    }
+
+}
+
+/// Result of a replay checkpoint
+pub enum Checkpoint {
+    /// The recording doesn't have a hash after this frame
+    None,
+    Matched,
+    /// The replay diverged from the recording
+    Mismatched { frame: u32, expected: u64, actual: u64 },
+}
+
+/// Feeds the inputs of a recording back to a client, see `GameClient::replay`
+pub struct Replay {
+    data: Box<[u8]>,
+    offset: usize,
+    frame: u32,
+    pub checkpoints: u32,
+    pub mismatches: u32,
+}
+
+impl Replay {
+
+    /// Reads the header of `recording` and overwrites the matching parameters of `init`
+    pub fn new(recording: Box<[u8]>, init: &mut GameClientInit) -> Result<Self, Error> {
+        let mut reader = Reader { data: &recording, offset: 0 };
+        if reader.read_slice(4)? != RECORDING_MAGIC {
+            return Err(save_err!("Data is not an input recording"));
+        }
+
+        let version = reader.read_u32()?;
+        if version != RECORDING_VERSION {
+            return Err(save_err!("Input recording version {} is not supported (expected {})", version, RECORDING_VERSION));
+        }
+
+        let width = reader.read_f32()?;
+        let height = reader.read_f32()?;
+        init.view_size(width, height);
+        init.pixel_ratio(reader.read_f32()?);
+        init.max_texture_size(reader.read_u32()?);
+        init.seed(reader.read_u32()?);
+        init.language(reader.read_str()?.to_string());
+
+        let offset = reader.offset;
+        Ok(Replay { data: recording, offset, frame: 0, checkpoints: 0, mismatches: 0 })
+    }
+
+    pub fn finished(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    /// Calls `apply` with the inputs of the next frame, up to and including its update.
+    /// Returns false if the recording is finished or invalid.
+    pub fn next_frame(&mut self, mut apply: impl FnMut(InputEvent)) -> bool {
+        let mut reader = Reader { data: &self.data, offset: self.offset };
+        let mut updated = false;
+        while !updated && reader.offset < self.data.len() {
+            match reader.read_event() {
+                Ok(Event::Input(input)) => {
+                    updated = matches!(input, InputEvent::Update { .. });
+                    apply(input);
+                },
+                Ok(Event::Checkpoint { .. }) => {
+                    // Checkpoints are read by `checkpoint` after the update
+                },
+                Err(mut e) => {
+                    e.merge(save_err!("Input recording is corrupted, the replay is stopped at frame {}", self.frame));
+                    log_err!(e);
+                    reader.offset = self.data.len();
+                }
+            }
+        }
+
+        self.offset = reader.offset;
+        if updated {
+            self.frame += 1;
+        }
+
+        updated
+    }
+
+    /// Compares the hash recorded after the last frame with `hash`. `hash` is only called if the recording has one.
+    pub fn checkpoint(&mut self, hash: impl FnOnce() -> u64) -> Checkpoint {
+        let mut reader = Reader { data: &self.data, offset: self.offset };
+        let Ok(Event::Checkpoint { hash: expected }) = reader.read_event() else {
+            return Checkpoint::None;
+        };
+
+        self.offset = reader.offset;
+        self.checkpoints += 1;
+
+        let actual = hash();
+        if actual == expected {
+            Checkpoint::Matched
+        } else {
+            self.mismatches += 1;
+            Checkpoint::Mismatched { frame: self.frame - 1, expected, actual }
+        }
+    }
+
+}
+
+/// Bounds checked reader. Recordings come from bug reports and can't be trusted.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+
+    fn read_event(&mut self) -> Result<Event<'a>, Error> {
+        use InputEvent::*;
+
+        let offset = self.offset;
+        let input = match self.read_u8()? {
+            UPDATE => Update { time: f64::from_le_bytes(self.read_array()?) },
+            MOUSE_POSITION => MousePosition { x: self.read_f32()?, y: self.read_f32()? },
+            MOUSE_INSIDE => MouseInside { inside: self.read_bool()? },
+            MOUSE_BUTTONS => MouseButtons { button: self.read_u8()?, pressed: self.read_bool()? },
+            MOUSE_WHEEL => MouseWheel { delta_x: self.read_f32()?, delta_y: self.read_f32()? },
+            TOUCH => {
+                let phase = match self.read_u8()? {
+                    0 => TouchPhase::Start,
+                    1 => TouchPhase::Move,
+                    2 => TouchPhase::End,
+                    3 => TouchPhase::Cancel,
+                    value => { return Err(save_err!("Invalid touch phase {} at offset {}", value, offset)); }
+                };
+                Touch { phase, id: self.read_u32()?, x: self.read_f32()?, y: self.read_f32()? }
+            },
+            KEYS => {
+                let pressed = self.read_bool()?;
+                let repeat = self.read_bool()?;
+                Keys { key_name: self.read_str()?, pressed, repeat }
+            },
+            MODIFIERS => {
+                let bits = self.read_u8()?;
+                Modifiers { alt: bits & 0b0001 != 0, ctrl: bits & 0b0010 != 0, shift: bits & 0b0100 != 0, meta: bits & 0b1000 != 0 }
+            },
+            TEXT => Text { text: self.read_str()? },
+            COMPOSITION => {
+                let done = self.read_bool()?;
+                Composition { text: self.read_str()?, done }
+            },
+            RESIZE => Resize { width: self.read_u32()?, height: self.read_u32()?, pixel_ratio: self.read_f32()? },
+            RESTORE => {
+                let size = self.read_u32()? as usize;
+                Restore { bytes: self.read_slice(size)? }
+            },
+            CHECKPOINT => {
+                return Ok(Event::Checkpoint { hash: u64::from_le_bytes(self.read_array()?) });
+            },
+            id => {
+                return Err(save_err!("Unknown input event {} at offset {}", id, offset));
+            }
+        };
+
+        Ok(Event::Input(input))
+    }
+
+    fn read_slice(&mut self, size: usize) -> Result<&'a [u8], Error> {
+        let bytes = self.offset.checked_add(size)
+            .and_then(|end| self.data.get(self.offset..end) )
+            .ok_or_else(|| save_err!("Input recording is truncated at offset {}", self.offset) )?;
+        self.offset += size;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_slice(N)?);
+        Ok(array)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    fn read_bool(&mut self) -> Result<bool, Error> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, Error> {
+        Ok(f32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_str(&mut self) -> Result<&'a str, Error> {
+        let size = self.read_u32()? as usize;
+        let offset = self.offset;
+        ::std::str::from_utf8(self.read_slice(size)?)
+            .map_err(|_| save_err!("Invalid utf-8 string at offset {}", offset) )
+    }
+
+}
@@ -1,5 +1,5 @@
 use zerocopy_derive::{Immutable, IntoBytes, FromBytes};
-use std::ops::{SubAssign, Sub};
+use std::ops::{Add, AddAssign, SubAssign, Sub, Mul, Div};
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, FromBytes, IntoBytes, Immutable)]
 #[repr(C)]
@@ -13,8 +13,27 @@ impl PositionF32 {
     pub fn splat(&self) -> [f32; 2] {
         [self.x, self.y]
     }
+
+    pub fn length(&self) -> f32 {
+        f32::sqrt((self.x * self.x) + (self.y * self.y))
+    }
+}
+
+
+impl Add for PositionF32 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        PositionF32 { x: self.x + rhs.x, y: self.y + rhs.y }
+    }
 }
 
+impl AddAssign for PositionF32 {
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Self) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
 
 impl Sub for PositionF32 {
     type Output = Self;
@@ -31,6 +50,20 @@ impl SubAssign for PositionF32 {
     }
 }
 
+impl Mul<f32> for PositionF32 {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self::Output {
+        PositionF32 { x: self.x * rhs, y: self.y * rhs }
+    }
+}
+
+impl Div<f32> for PositionF32 {
+    type Output = Self;
+    fn div(self, rhs: f32) -> Self::Output {
+        PositionF32 { x: self.x / rhs, y: self.y / rhs }
+    }
+}
+
 #[derive(Default, Debug, Copy, Clone, PartialEq, FromBytes, IntoBytes, Immutable)]
 #[repr(C)]
 pub struct SizeF32 {
@@ -39,6 +72,7 @@ pub struct SizeF32 {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Default, FromBytes, IntoBytes, Immutable)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct AABB {
     pub left: f32,
     pub top: f32,
@@ -66,6 +100,10 @@ impl AABB {
     pub fn height(&self) -> f32 {
         self.bottom - self.top
     }
+
+    pub fn center(&self) -> PositionF32 {
+        PositionF32 { x: (self.left + self.right) * 0.5, y: (self.top + self.bottom) * 0.5 }
+    }
 }
 
 //
@@ -93,9 +131,8 @@ pub const fn aabb(position: PositionF32, size: SizeF32) -> AABB {
 pub fn split_csv<const MAX_ARGS: usize, CB: FnMut(&[&str])>(csv: &str, mut callback: CB) {
     let mut start = 0;
     let mut end = 0;
-    let mut chars_iter = csv.chars();
     let mut args: [&str; MAX_ARGS] = [""; MAX_ARGS];
-    while let Some(c) = chars_iter.next() {
+    for c in csv.chars() {
         end += 1;
         if c == '\n' {
             let line = &csv[start..end];
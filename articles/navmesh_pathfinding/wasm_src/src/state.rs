@@ -4,8 +4,10 @@ pub mod obstacles;
 pub mod final_demo;
 
 use zerocopy_derive::{Immutable, IntoBytes, TryFromBytes};
-use crate::data::gui::GuiEvent;
+use crate::data::{GlobalParams, actions::Action, gui::{Gui, GuiEvent}, touch::TouchPoint};
+use crate::shared::{PositionF32, SizeF32, pos, size};
 use crate::GameClient;
+use crate::store::StoreLoad;
 
 #[derive(Default, Debug, PartialEq, Eq, Copy, Clone, TryFromBytes, IntoBytes, Immutable)]
 #[repr(u32)]
@@ -29,9 +31,10 @@ pub enum GameInputType {
     PlacePawn,
 }
 
-#[derive(Default, Copy, Clone)]
+#[derive(Default, Copy, Clone, StoreLoad)]
 pub struct GameState {
     pub hovered_entity: Option<hecs::Entity>,
+    pub follow_entity: Option<hecs::Entity>,
     pub input_type: GameInputType,
     pub value: GameStateValue,
     pub scroll_view: bool,
@@ -45,9 +48,18 @@ pub fn handle_gui_events(client: &mut GameClient) {
                 client.state.value = new_state;
             },
             GuiEvent::SetInputType(new_input) => {
-                client.data.world.clear_selected_sprites();
-                client.state.input_type = new_input;
+                set_input_type(client, new_input);
             }
+            GuiEvent::SetFollowSelection(follow) => {
+                client.state.follow_entity = match follow {
+                    true => client.data.world.selected_sprites().first().copied(),
+                    false => None,
+                };
+
+                if follow && client.state.follow_entity.is_none() {
+                    client.data.gui.set_follow_selection(false);
+                }
+            },
             GuiEvent::SetDebugFlags(new_flags) => {
                 client.data.globals.debug_flags = new_flags;
             },
@@ -57,44 +69,218 @@ pub fn handle_gui_events(client: &mut GameClient) {
             GuiEvent::ResetPawnPosition => {
 
             },
+            GuiEvent::SetBinding(action, slot, binding) => {
+                let mut bindings = client.data.bindings;
+                bindings.set(action, slot, binding);
+                client.data.set_bindings(bindings);
+            },
+            GuiEvent::ResetBindings => {
+                client.data.set_bindings(client.data.assets.key_bindings);
+            },
+            GuiEvent::InspectEntity(entity) => {
+                client.data.world.clear_selected_sprites();
+                client.data.world.select_sprite(entity);
+            },
+            GuiEvent::DuplicateEntity(entity) => {
+                client.data.duplicate_sprite(entity);
+            },
+            GuiEvent::DeleteEntity(entity) => {
+                client.data.world.delete_sprite(entity);
+            },
+            GuiEvent::EditEntity(snapshot) => {
+                client.data.world.apply_snapshot(&snapshot);
+            },
         }
     }
 }
 
+const ZOOM_SPEED: f32 = 0.0015;
+
+/// Camera speed when panning with the keyboard or the screen edges, in screen pixels per seconds
+const PAN_SPEED: f32 = 900.0;
+/// How fast the camera reaches the pan speed
+const PAN_ACCELERATION: f32 = 12.0;
+/// How fast the camera slows down once the user stops panning
+const PAN_FRICTION: f32 = 6.0;
+/// Distance from the screen edges (in pixels) that triggers edge scrolling
+const EDGE_SCROLL_SIZE: f32 = 12.0;
+/// How fast the camera catches up with the followed entity
+const FOLLOW_SPEED: f32 = 6.0;
+
 pub fn common_inputs(game: &mut GameClient) {
+    if game.data.globals.actions.just_pressed(Action::CycleState) {
+        cycle_state(game);
+    }
+
     let globals = &mut game.data.globals;
     let state = &mut game.state;
+    let gui = &mut game.data.gui;
+    let seconds = globals.time_delta / 1000.0;
     
-    if globals.middle_mouse_just_pressed() {
+    if globals.actions.just_pressed(Action::Pan) {
         state.scroll_view = true;
-    } else if globals.middle_mouse_released() {
+        release_follow(state, gui);
+    } else if globals.actions.released(Action::Pan) {
         state.scroll_view = false;
     }
 
-    if state.scroll_view {
-        if let Some(delta) = globals.mouse_delta() {
-            globals.view_offset -= delta;
-            globals.flags.set_update_view_offset();
+    if let Some(fingers) = globals.touches.two_fingers() {
+        release_follow(state, gui);
+        touch_gesture(globals, gui, fingers);
+    } else if state.scroll_view {
+        match globals.mouse_delta() {
+            Some(delta) => {
+                // Mouse delta is in screen space, the view offset is in world space
+                let world_delta = delta / globals.view_scale;
+                globals.view_offset -= world_delta;
+                globals.flags.set_update_view_offset();
+
+                // Track the drag speed so the view keeps sliding once the button is released
+                if seconds > 0.0 {
+                    globals.view_velocity = world_delta * (-1.0 / seconds);
+                }
+            },
+            None => {
+                globals.view_velocity = globals.view_velocity * f32::exp(-PAN_FRICTION * seconds);
+            }
+        }
+    } else if let Some(entity) = state.follow_entity {
+        match game.data.world.sprite_center(entity) {
+            Some(center) if !globals.actions.any_pan() => follow(globals, gui, center, seconds),
+            _ => release_follow(state, gui),
         }
+    } else {
+        smooth_pan(globals, gui, seconds);
+    }
+
+    if globals.mouse_wheel_delta != 0.0 && gui.position_outside_gui(globals.mouse_position) {
+        let factor = f32::exp(-globals.mouse_wheel_delta * ZOOM_SPEED);
+        globals.zoom_view(factor, globals.mouse_position);
+    }
+
+    let world = game.data.terrain.world_size();
+    if world.width > 0.0 && world.height > 0.0 {
+        globals.clamp_view(world, visible_size(globals, gui));
     }
 }
 
-impl crate::store::StoreLoad for GameState {
-    fn store(&mut self, writer: &mut crate::store::StoreWriter) {
-        writer.write_entity_option(self.hovered_entity);
-        writer.write(&self.input_type);
-        writer.write(&self.value);
-        writer.write_bool(self.scroll_view);
+/// Shortcuts of the states that can edit the world
+pub fn edit_actions(game: &mut GameClient) {
+    let actions = game.data.globals.actions;
+    if actions.just_pressed(Action::Delete) {
+        game.data.world.delete_selected_sprites();
+    }
+
+    if actions.just_pressed(Action::PlaceHouse) {
+        set_input_type(game, GameInputType::PlaceHouse);
     }
+}
 
-    fn load(reader: &mut crate::store::StoreReader) -> Result<Self, crate::error::Error> {
-        let mut state = GameState::default();
+fn set_input_type(game: &mut GameClient, input_type: GameInputType) {
+    if let Some(hovered) = game.state.hovered_entity.take() {
+        game.data.world.clear_sprite_highlight(hovered);
+    }
 
-        state.hovered_entity = reader.try_read_entity_option()?;
-        state.input_type = reader.try_read()?;
-        state.value = reader.try_read()?;
-        state.scroll_view = reader.try_read_bool()?;
+    game.data.world.clear_selected_sprites();
+    game.data.gui.close_context_menu();
+    game.state.input_type = input_type;
+    game.data.gui.set_state(game.state.value, input_type);
+}
 
-        Ok(state)
+/// Switch to the next demo, in the same order as the gui
+fn cycle_state(game: &mut GameClient) {
+    use GameStateValue::*;
+    let next = match game.state.value {
+        Uninitialized => { return; },
+        Generation => Navigation,
+        Navigation => Obstacles,
+        Obstacles => FinalDemo,
+        FinalDemo => Generation,
+    };
+
+    game.state.value = next;
+    set_input_type(game, GameInputType::Select);
+}
+
+/// Two fingers pan and pinch zoom
+fn touch_gesture(globals: &mut GlobalParams, gui: &Gui, [a, b]: [TouchPoint; 2]) {
+    let center_old = (a.position_old + b.position_old) / 2.0;
+    let center = (a.position + b.position) / 2.0;
+    if !gui.position_outside_gui(center_old) {
+        return;
+    }
+
+    globals.view_velocity = pos(0.0, 0.0);
+    globals.view_offset += (center - center_old) / globals.view_scale;
+    globals.flags.set_update_view_offset();
+
+    let distance_old = (a.position_old - b.position_old).length();
+    let distance = (a.position - b.position).length();
+    if distance_old > 1.0 && distance > 1.0 {
+        globals.zoom_view(distance / distance_old, center);
+    }
+}
+
+/// Size of the screen area not covered by the gui
+fn visible_size(globals: &GlobalParams, gui: &Gui) -> SizeF32 {
+    size(globals.view_size.width, f32::min(globals.view_size.height, gui.top()))
+}
+
+fn release_follow(state: &mut GameState, gui: &mut Gui) {
+    if state.follow_entity.take().is_some() {
+        gui.set_follow_selection(false);
+    }
+}
+
+/// Smoothly move the camera so that `center` (in world space) ends up in the middle of the screen
+fn follow(globals: &mut GlobalParams, gui: &Gui, center: PositionF32, seconds: f32) {
+    let visible = visible_size(globals, gui);
+    let target = (pos(visible.width, visible.height) / (2.0 * globals.view_scale)) - center;
+    let t = 1.0 - f32::exp(-FOLLOW_SPEED * seconds);
+    let delta = (target - globals.view_offset) * t;
+
+    globals.view_velocity = pos(0.0, 0.0);
+    if delta.length() > 0.01 {
+        globals.view_offset += delta;
+        globals.flags.set_update_view_offset();
     }
 }
+
+/// Move the camera using the held keys, the screen edges and the remaining drag velocity
+fn smooth_pan(globals: &mut GlobalParams, gui: &Gui, seconds: f32) {
+    let mut direction = pos(0.0, 0.0);
+    let actions = globals.actions;
+    if actions.pressed(Action::PanLeft) { direction.x -= 1.0; }
+    if actions.pressed(Action::PanRight) { direction.x += 1.0; }
+    if actions.pressed(Action::PanUp) { direction.y -= 1.0; }
+    if actions.pressed(Action::PanDown) { direction.y += 1.0; }
+
+    let mouse = globals.mouse_position;
+    if globals.mouse_inside && gui.position_outside_gui(mouse) {
+        let view = globals.view_size;
+        if mouse.x < EDGE_SCROLL_SIZE { direction.x -= 1.0; }
+        if mouse.x > view.width - EDGE_SCROLL_SIZE { direction.x += 1.0; }
+        if mouse.y < EDGE_SCROLL_SIZE { direction.y -= 1.0; }
+        if mouse.y > view.height - EDGE_SCROLL_SIZE { direction.y += 1.0; }
+    }
+
+    let panning = direction.x != 0.0 || direction.y != 0.0;
+    if panning {
+        direction = direction / direction.length();
+    }
+
+    // Moving the camera right means moving the world left
+    let target = direction * (-PAN_SPEED / globals.view_scale);
+    let rate = if panning { PAN_ACCELERATION } else { PAN_FRICTION };
+    let t = 1.0 - f32::exp(-rate * seconds);
+    globals.view_velocity += (target - globals.view_velocity) * t;
+
+    if !panning && globals.view_velocity.length() < 1.0 {
+        globals.view_velocity = pos(0.0, 0.0);
+        return;
+    }
+
+    globals.view_offset += globals.view_velocity * seconds;
+    globals.flags.set_update_view_offset();
+}
+
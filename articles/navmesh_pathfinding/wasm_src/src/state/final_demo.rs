@@ -1,7 +1,8 @@
 use crate::shared::pos;
 use crate::GameClient;
-use super::{GameStateValue, GameInputType, common_inputs};
+use super::{GameStateValue, GameInputType, common_inputs, edit_actions};
 
+#[allow(dead_code)]
 pub fn init(game: &mut GameClient) {
     game.data.reset();
     game.data.initialize_terrain(32, 16);
@@ -12,4 +13,5 @@ pub fn init(game: &mut GameClient) {
 
 pub fn update(game: &mut GameClient) {
     common_inputs(game);
+    edit_actions(game);
 }
@@ -1,7 +1,8 @@
 use crate::data::base::StaticSprite;
 use crate::shared::{SizeF32, PositionF32, pos};
 use crate::GameClient;
-use super::{GameStateValue, GameInputType, common_inputs};
+use crate::data::actions::Action;
+use super::{GameStateValue, GameInputType, common_inputs, edit_actions, set_input_type};
 
 // for (_, sprite) in game.data.world.iter_all_sprites() {
 //     game.data.debug.draw_rect(sprite.rect(), 2.0, [255, 0, 0, 255]);
@@ -29,12 +30,18 @@ pub fn init(game: &mut GameClient) {
 
 pub fn update(game: &mut GameClient) {
     common_inputs(game);
+    edit_actions(game);
     set_insert_sprite(game);
 
-    if game.data.globals.primary_mouse_just_pressed() {
-        if game.data.gui.position_outside_gui(game.data.globals.mouse_position) {
-            primary_mouse_actions(game);
-        }
+    let outside_gui = game.data.gui.position_outside_gui(game.data.globals.mouse_position);
+    if game.data.globals.actions.just_pressed(Action::Select) && outside_gui {
+        game.data.gui.close_context_menu();
+        primary_mouse_actions(game);
+    }
+
+    if game.data.globals.actions.just_pressed(Action::Command) && outside_gui {
+        game.data.gui.close_context_menu();
+        secondary_mouse_actions(game);
     }
 
     if game.data.globals.mouse_moved() {
@@ -64,8 +71,9 @@ fn set_insert_sprite(game: &mut GameClient) {
 }
 
 fn set_insert_sprite_value(game: &mut GameClient, sprite: StaticSprite) {
-    let position = game.data.globals.mouse_position;
-    if game.data.gui.position_outside_gui(position) {
+    let globals = &game.data.globals;
+    if game.data.gui.position_outside_gui(globals.mouse_position) {
+        let position = globals.mouse_world_position();
         game.data.world.set_insert_sprite(center_sprite(position, sprite.texcoord.size()), sprite);
     } else {
         game.data.world.clear_insert_sprite();
@@ -73,8 +81,7 @@ fn set_insert_sprite_value(game: &mut GameClient, sprite: StaticSprite) {
 }
 
 fn primary_mouse_actions(game: &mut GameClient) {
-    let globals = &game.data.globals;
-    let position = globals.mouse_position - globals.view_offset;
+    let position = game.data.globals.mouse_world_position();
     match game.state.input_type {
         GameInputType::PlaceCastle => {
             let sprite = game.data.assets.atlas.castle;
@@ -92,30 +99,44 @@ fn primary_mouse_actions(game: &mut GameClient) {
             game.data.world.delete_sprite_at_position(position);
         },
         GameInputType::Select => {
-            game.data.world.clear_selected_sprites();
+            // Shift-click adds to the current selection
+            if !game.data.globals.modifiers.shift() {
+                game.data.world.clear_selected_sprites();
+            }
             game.data.world.select_sprite_at_position(position);
         }
     }
 }
 
+/// Cancels the placement tools. In select mode, opens the context menu of the entity under the mouse
+/// or sends the selected pawns to the mouse position.
+fn secondary_mouse_actions(game: &mut GameClient) {
+    if game.state.input_type != GameInputType::Select {
+        set_input_type(game, GameInputType::Select);
+        return;
+    }
+
+    let position = game.data.globals.mouse_world_position();
+    match game.data.world.sprite_at_position(position) {
+        Some(entity) => game.data.gui.open_context_menu(entity, game.data.globals.mouse_position),
+        None => { game.data.world.order_selected_pawns_move(position); }
+    }
+}
+
 fn mouse_moved_actions(game: &mut GameClient) {
-    match game.state.input_type {
-        GameInputType::Delete => {
-            let globals = &game.data.globals;
-            let position = globals.mouse_position - globals.view_offset;
-            let hovered_new = game.data.world.sprite_at_position(position);
-            let hovered_old = game.state.hovered_entity;
-            if hovered_new != hovered_old {
-                if let Some(old) = hovered_old {
-                    game.data.world.clear_sprite_highlight(old);
-                }
-                if let Some(new) = hovered_new {
-                    game.data.world.set_sprite_highlight(new, [255, 0, 0]);
-                }
-                game.state.hovered_entity = hovered_new;
+    if game.state.input_type == GameInputType::Delete {
+        let position = game.data.globals.mouse_world_position();
+        let hovered_new = game.data.world.sprite_at_position(position);
+        let hovered_old = game.state.hovered_entity;
+        if hovered_new != hovered_old {
+            if let Some(old) = hovered_old {
+                game.data.world.clear_sprite_highlight(old);
             }
-        },
-        _ => {}
+            if let Some(new) = hovered_new {
+                game.data.world.set_sprite_highlight(new, [255, 0, 0]);
+            }
+            game.state.hovered_entity = hovered_new;
+        }
     }
 }
 
@@ -1,55 +1,178 @@
 //! Serializer and Deserializer for the application data
-use zerocopy::{transmute, FromBytes, Immutable, IntoBytes, TryFromBytes};
+use std::borrow::Cow;
+use zerocopy::{transmute, Immutable, IntoBytes, TryFromBytes};
 use crate::error::Error;
 
+pub use navmesh_store_derive::StoreLoad;
+
+pub mod base64;
+mod compress;
+
+#[cfg(test)]
+mod tests;
+
 pub const U32_SIZE: usize = size_of::<u32>();
 pub const MIN_ALIGN: usize = U32_SIZE;
 
+/// First 4 bytes of every save
+pub const STORE_MAGIC: u32 = u32::from_le_bytes(*b"NMPD");
+
+/// Must be incremented when the header or the encoding of the primitive types change.
+/// Stored structs are split in tagged sections, so adding, removing or reordering their fields doesn't need a new version.
+pub const STORE_VERSION: u32 = 2;
+
+/// Size of the blob header: magic, version, flags and size of the uncompressed content
+pub const HEADER_SIZE: usize = 4 * U32_SIZE;
+
+/// The content of the save is compressed with `compress::compress`
+const FLAG_COMPRESSED: u32 = 0x1;
+
 pub trait StoreLoad: Sized {
     fn store(&mut self, writer: &mut StoreWriter);
     fn load(reader: &mut StoreReader) -> Result<Self, Error>;
+    /// Lists the stored types. Must be updated with `store` and `load`.
+    fn schema(schema: &mut Schema);
 }
 
-pub struct StoreWriter {
-    pub data: Vec<u8>,
-    pub data_offset: usize,
+/// Tag of a stored section (FNV-1a of its name)
+pub const fn tag(name: &str) -> u32 {
+    let bytes = name.as_bytes();
+    let mut hash: u32 = 0x811c9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x01000193);
+        i += 1;
+    }
+    hash
 }
 
-impl StoreWriter {
+/// Hash of the stored types of a section, built from the type names and sizes (FNV-1a)
+pub struct Schema {
+    hash: u32,
+}
+
+impl Schema {
+
+    pub fn of<T: StoreLoad>() -> u32 {
+        let mut schema = Schema { hash: 0x811c9dc5 };
+        T::schema(&mut schema);
+        schema.hash
+    }
 
-    pub fn new() -> Self {
-        StoreWriter {
-            data: vec![0u8; 1024*1024],    // 1mb should be more than enough
-            data_offset: 0
+    pub fn field<T>(&mut self, name: &str) -> &mut Self {
+        self.write(name.as_bytes());
+        self.write(&(size_of::<T>() as u32).to_le_bytes());
+        self
+    }
+
+    /// A field holding values of type `T`, ex: `Vec<T>` with the `container` "Vec". The size of `T` is hashed
+    /// because the size of the container doesn't depend on its values.
+    pub fn values<T>(&mut self, name: &str, container: &str) -> &mut Self {
+        self.write(name.as_bytes());
+        self.write(container.as_bytes());
+        self.write(&(size_of::<T>() as u32).to_le_bytes());
+        self
+    }
+
+    /// A string field
+    pub fn string(&mut self, name: &str) -> &mut Self {
+        self.write(name.as_bytes());
+        self.write(b"str");
+        self
+    }
+
+    pub fn nested<T: StoreLoad>(&mut self, name: &str) -> &mut Self {
+        self.write(name.as_bytes());
+        T::schema(self);
+        self
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= byte as u32;
+            self.hash = self.hash.wrapping_mul(0x01000193);
         }
     }
 
-    pub fn data(self) -> Box<[u8]> {
-        self.data[0..self.data_offset].to_vec().into_boxed_slice()
+}
+
+/// A top level part of a save. The header stores the schema hash of every section, so loading a save
+/// made with other versions of the stored structs can be reported.
+#[derive(Copy, Clone)]
+pub struct StoreSection {
+    pub name: &'static str,
+    pub hash: u32,
+}
+
+impl StoreSection {
+    pub fn of<T: StoreLoad>(name: &'static str) -> Self {
+        StoreSection { name, hash: Schema::of::<T>() }
     }
+}
 
-    pub fn write<T: IntoBytes+Immutable>(&mut self, value: &T) {
-        assert!(align_of::<T>() == MIN_ALIGN, "Data alignment must be 4 bytes");
+pub struct StoreWriter {
+    data: Vec<u8>,
+}
+
+impl StoreWriter {
 
-        let size = size_of::<T>();
-        if self.must_realloc(size) {
-            self.realloc(size);
+    /// Creates a writer and writes the schema hash of the sections. The blob header is written by `finish`.
+    pub fn new(sections: &[StoreSection]) -> Self {
+        let mut writer = StoreWriter {
+            data: Vec::with_capacity(0x10000),
+        };
+
+        writer.data.resize(HEADER_SIZE, 0);
+        writer.write(&(sections.len() as u32));
+        for section in sections {
+            writer.write(&[tag(section.name), section.hash]);
         }
 
-        value.write_to_prefix(self.remaining_bytes()).unwrap();
-        self.data_offset += size;
+        writer
     }
 
-    pub fn write_option<T: IntoBytes+Immutable>(&mut self, op_value: &Option<T>) {
-        let total_size = size_of::<T>() + U32_SIZE;
-        if self.must_realloc(total_size) {
-            self.realloc(total_size);
+    /// Writes a section tagged with `name`. `store` writes the content of the section.
+    /// The length of the section is written before its content, so readers can skip sections they don't know.
+    pub fn write_section<F: FnOnce(&mut StoreWriter)>(&mut self, name: &str, store: F) {
+        self.write(&[tag(name), 0]);
+        let start = self.data.len();
+
+        store(self);
+
+        let length = (self.data.len() - start) as u32;
+        self.data[(start-U32_SIZE)..start].copy_from_slice(length.as_bytes());
+    }
+
+    /// Writes the blob header and the checksum footer, and returns the save. See `unpack`.
+    pub fn finish(mut self, compress: bool) -> Box<[u8]> {
+        let size = (self.data.len() - HEADER_SIZE) as u32;
+        let mut flags = 0;
+        if compress {
+            let compressed = compress::compress(&self.data[HEADER_SIZE..]);
+            self.data.truncate(HEADER_SIZE);
+            self.data.extend_from_slice(&compressed);
+            flags |= FLAG_COMPRESSED;
         }
 
+        let header = [STORE_MAGIC, STORE_VERSION, flags, size];
+        self.data[..HEADER_SIZE].copy_from_slice(header.as_bytes());
+
+        let crc = compress::crc32(&self.data);
+        self.data.extend_from_slice(&crc.to_le_bytes());
+
+        self.data.into_boxed_slice()
+    }
+
+    pub fn write<T: IntoBytes+Immutable>(&mut self, value: &T) {
+        assert!(align_of::<T>() == MIN_ALIGN, "Data alignment must be 4 bytes");
+        self.data.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn write_option<T: IntoBytes+Immutable>(&mut self, op_value: &Option<T>) {
         self.write_u32(op_value.is_some() as u32);
         if let Some(value) = op_value.as_ref() {
-            value.write_to_prefix(self.remaining_bytes()).unwrap();
-            self.data_offset += size_of::<T>();
+            self.data.extend_from_slice(value.as_bytes());
         }
     }
 
@@ -57,112 +180,86 @@ impl StoreWriter {
         assert!(align_of::<T>() <= MIN_ALIGN, "Data alignment must up to 4 bytes");
 
         let values_count = values.len();
-        let values_size = size_of::<T>() * values_count;
+        let values_size = size_of_val(values);
         let values_size_padded = crate::shared::align_up(values_size, MIN_ALIGN);
-        let total_size = U32_SIZE + U32_SIZE + values_size_padded;
-        if self.must_realloc(total_size) {
-            self.realloc(total_size);
-        }
 
         self.write(&[values_count as u32, values_size_padded as u32]);
         if values_count == 0 {
             return;
         }
 
-        values.write_to_prefix(self.remaining_bytes()).unwrap();
-        self.data_offset += values_size_padded;
+        self.data.extend_from_slice(values.as_bytes());
+        self.pad(values_size_padded - values_size);
     }
 
     pub fn write_str(&mut self, value: &str) {
         // Strings must be padded to 4 bytes
         let length = value.len();
         let padded_length = crate::shared::align_up(length, MIN_ALIGN);
-        let total_length = U32_SIZE + U32_SIZE + padded_length;
-        if self.must_realloc(total_length) {
-            self.realloc(total_length);
-        }
 
         self.write_u32(length as u32);
         self.write_u32(padded_length as u32);
-        value.write_to_prefix(self.remaining_bytes()).unwrap();
-
-        self.data_offset += padded_length;
-    }
-
-    pub fn write_string_hashmap<T: IntoBytes+Immutable>(&mut self, values: &fnv::FnvHashMap<String, T>) {
-        assert!(align_of::<T>() == MIN_ALIGN, "Data alignment must be 4 bytes");
-
-        let values_count = values.len() as u32;
-        self.write(&values_count);
-
-        if values_count == 0 {
-            return;
-        }
-        
-        for (key, value) in values.iter() {
-            self.write_str(key);
-            self.write(value);
-        }
-    }
-
-    pub fn write_string_array_hashmap(&mut self, values: &fnv::FnvHashMap<String, Vec<u8>>) {
-        let values_count = values.len() as u32;
-        self.write(&values_count);
-
-        if values_count == 0 {
-            return;
-        }
-        
-        for (key, value) in values.iter() {
-            self.write_str(key);
-            self.write_array(value);
-        }
+        self.data.extend_from_slice(value.as_bytes());
+        self.pad(padded_length - length);
     }
 
     pub fn write_bool(&mut self, value: bool) {
-        if self.must_realloc(U32_SIZE) {
-            self.realloc(U32_SIZE);
-        }
-
         self.write_u32(value as u32)
     }
 
     pub fn write_entity_option(&mut self, value: Option<hecs::Entity>) { 
-        let size = U32_SIZE + U32_SIZE;
-        if self.must_realloc(size) {
-            self.realloc(size);
-        }
-
         let raw_values: [u32; 2] = value
             .map(|v| v.to_bits() )
             .map(|v| transmute!(v) )
             .unwrap_or([0, 0]);
 
-        raw_values.write_to_prefix(self.remaining_bytes()).unwrap();
-        self.data_offset += size;
+        self.write(&raw_values);
     }
 
-    fn must_realloc(&self, size: usize) -> bool {
-        self.data[self.data_offset..].len() < size
+    fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(value.as_bytes());
     }
 
-    fn remaining_bytes(&mut self) -> &mut [u8] {
-        &mut self.data[self.data_offset..]
+    fn pad(&mut self, size: usize) {
+        self.data.resize(self.data.len() + size, 0);
     }
 
-    fn write_u32(&mut self, value: u32) {
-        // The caller function must ensure there is enough remaining bytes
-        value.write_to_prefix(self.remaining_bytes()).unwrap();
-        self.data_offset += U32_SIZE;
-    }  
+}
+
+/// Checks the header and the checksum of a save, and returns its content. Compressed saves are decompressed.
+///
+/// A save is: magic, version, flags, content size, content, crc32 of everything before the crc.
+pub fn unpack(bytes: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    if bytes.len() < HEADER_SIZE + U32_SIZE {
+        return Err(save_err!("Save is too small to contain a header ({} bytes)", bytes.len()));
+    }
+
+    let (content, footer) = bytes.split_at(bytes.len() - U32_SIZE);
+    let mut reader = StoreReader { data: content, data_offset: 0 };
+    let [magic, version, flags, size]: [u32; 4] = reader.try_read()?;
+    if magic != STORE_MAGIC {
+        return Err(save_err!("Data is not a save (magic {:08x})", magic));
+    }
 
-    #[inline(never)]
-    #[cold]
-    fn realloc(&mut self, min_size: usize) {
-        self.data.reserve_exact(crate::shared::align_up(min_size, 0x10000));
-        unsafe { self.data.set_len(self.data.capacity()); }
+    if version != STORE_VERSION {
+        return Err(save_err!("Save format version {} is not supported (expected {})", version, STORE_VERSION));
     }
 
+    let crc = u32::from_le_bytes([footer[0], footer[1], footer[2], footer[3]]);
+    let expected_crc = compress::crc32(content);
+    if crc != expected_crc {
+        return Err(save_err!("Save checksum doesn't match ({:08x} != {:08x}). The save is corrupted.", crc, expected_crc));
+    }
+
+    let body = &content[HEADER_SIZE..];
+    let size = size as usize;
+    if flags & FLAG_COMPRESSED != 0 {
+        compress::decompress(body, size).map(Cow::Owned)
+    } else if body.len() != size {
+        Err(save_err!("Save has {} bytes of content, but its declared size is {} bytes", body.len(), size))
+    } else {
+        Ok(Cow::Borrowed(body))
+    }
 }
 
 pub struct StoreReader<'a> {
@@ -172,18 +269,61 @@ pub struct StoreReader<'a> {
 
 impl<'a> StoreReader<'a> {
 
-    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
-        let reader = StoreReader {
+    /// Creates a reader over the content of a save returned by `unpack` and checks the sections schema.
+    /// Returns an error if the schema of a section changed, the save must not be loaded in this case.
+    pub fn new(data: &'a [u8], sections: &[StoreSection]) -> Result<Self, Error> {
+        let mut reader = StoreReader {
             data,
             data_offset: 0,
         };
 
+        let section_count: u32 = reader.try_read()?;
+        for _ in 0..section_count {
+            let [section_tag, hash]: [u32; 2] = reader.try_read()?;
+            let changed = sections.iter()
+                .find(|section| tag(section.name) == section_tag && section.hash != hash );
+
+            if let Some(section) = changed {
+                return Err(save_err!("Save section {:?} schema changed ({:08x} != {:08x})", section.name, hash, section.hash));
+            }
+        }
+
         Ok(reader)
     }
 
+    /// Reads the tagged sections until the end of the current section. `load` is called with the tag of each section
+    /// and returns `false` if it doesn't know the tag. Unknown sections are skipped, known sections must be read entirely.
+    pub fn read_sections<F>(&mut self, mut load: F) -> Result<(), Error>
+        where F: FnMut(&mut StoreReader<'a>, u32) -> Result<bool, Error>
+    {
+        while self.data_offset < self.data.len() {
+            let offset = self.data_offset;
+            let [section_tag, length]: [u32; 2] = self.try_read()?;
+            let start = self.data_offset;
+            self.read_bytes(length as usize)
+                .map_err(|mut e| { e.merge(save_err!("Section {:08x} at offset {} is truncated", section_tag, offset)); e } )?;
+
+            let mut section = StoreReader {
+                data: &self.data[..self.data_offset],
+                data_offset: start,
+            };
+
+            let known = load(&mut section, section_tag)?;
+            if known && section.data_offset != section.data.len() {
+                return Err(save_err!("Section {:08x} at offset {} has {} unread bytes", section_tag, offset, section.data.len() - section.data_offset));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn try_read<T: TryFromBytes+Immutable>(&mut self) -> Result<T, Error> {
-        let (value, _) = TryFromBytes::try_read_from_prefix(& self.data[self.data_offset..])
-            .map_err(|_| save_err!("Failed to read data") )?;
+        let offset = self.data_offset;
+        let bytes = self.data.get(offset..)
+            .ok_or_else(|| save_err!("Read offset {} is outside the save ({} bytes)", offset, self.data.len()) )?;
+
+        let (value, _) = TryFromBytes::try_read_from_prefix(bytes)
+            .map_err(|_| save_err!("Failed to read a {} at offset {} ({} bytes remaining)", ::std::any::type_name::<T>(), offset, bytes.len()) )?;
 
         self.data_offset += size_of::<T>();
 
@@ -191,7 +331,7 @@ impl<'a> StoreReader<'a> {
     }
 
     pub fn try_read_option<T: TryFromBytes+Immutable>(&mut self) -> Result<Option<T>, Error> {
-        let is_some = self.read_u32();
+        let is_some = self.read_u32()?;
         if is_some > 0 {
             self.try_read().map(Option::Some)
         } else {
@@ -215,83 +355,61 @@ impl<'a> StoreReader<'a> {
         }
     }
 
-    pub fn read_array<'b, T: Copy+FromBytes+Immutable>(&mut self) -> &'b [T] {
-        unsafe { self.read_array_transmute() }
-    }
-
-    // Read array of values that may not be safe to cast `FromBytes`
-    pub unsafe fn read_array_transmute<'b, T: Copy>(&mut self) -> &'b [T] {
-        let count = self.read_u32() as usize;
-        let values_size_padded = self.read_u32() as usize;
+    /// Reads an array written by `StoreWriter::write_array`. Values are copied, so the save doesn't need to be aligned
+    /// and values that are not valid for every bit pattern (ex: enums) are checked.
+    pub fn read_array<T: TryFromBytes+Immutable>(&mut self) -> Result<Vec<T>, Error> {
+        let offset = self.data_offset;
+        let count = self.read_u32()? as usize;
+        let values_size_padded = self.read_u32()? as usize;
         if count == 0 {
-            return &[];
+            return Ok(Vec::new());
         }
 
-        let total_size = count * size_of::<T>();
-        if total_size > self.remaining_size() {
-            panic!("Malformed data. Reading array would go outside buffer");
+        let value_size = size_of::<T>();
+        let values_bytes = self.read_bytes(values_size_padded)?;
+        let values_size = count.checked_mul(value_size)
+            .filter(|&size| size <= values_size_padded )
+            .ok_or_else(|| save_err!("Array at offset {} has {} values of {} bytes, but only {} bytes of data", offset, count, value_size, values_size_padded) )?;
+
+        let mut values = Vec::with_capacity(count);
+        for (index, value_bytes) in values_bytes[..values_size].chunks_exact(value_size).enumerate() {
+            let value = T::try_read_from_bytes(value_bytes)
+                .map_err(|_| save_err!("Array at offset {} has an invalid {} at index {}", offset, ::std::any::type_name::<T>(), index) )?;
+            values.push(value);
         }
 
-        let start_offset = self.data_offset;
-        self.data_offset += values_size_padded;
-
-        unsafe { ::std::slice::from_raw_parts(self.data.as_ptr().add(start_offset) as *const T, count) }
-    }
-
-    pub fn read_str(&mut self) -> &str {
-        let length = self.read_u32();
-        let length_padded = self.read_u32();
-        let str = unsafe {
-            let str_ptr = self.data.as_ptr().add(self.data_offset) as *const u8;
-            let str_bytes = ::std::slice::from_raw_parts(str_ptr, length as usize);
-            ::std::str::from_utf8(str_bytes).unwrap_or("UTF8 DECODING ERROR")
-        };
-
-        self.data_offset += length_padded as usize;
-
-        str
+        Ok(values)
     }
 
-    pub fn read_string_hashmap<T: FromBytes+Immutable>(&mut self) -> fnv::FnvHashMap<String, T> {
-        let mut out = fnv::FnvHashMap::default();
-        let count = self.read_u32() as usize;
-        if count == 0 {
-            return out;
-        }
-
-        for _ in 0..count {
-            let key = self.read_str().to_string();
-            let value = self.try_read().unwrap();
-            out.insert(key, value);
+    pub fn read_str(&mut self) -> Result<&'a str, Error> {
+        let offset = self.data_offset;
+        let length = self.read_u32()? as usize;
+        let length_padded = self.read_u32()? as usize;
+        if length > length_padded {
+            return Err(save_err!("String at offset {} has a length of {} bytes, but only {} bytes of data", offset, length, length_padded));
         }
 
-        out
+        let str_bytes = self.read_bytes(length_padded)?;
+        ::std::str::from_utf8(&str_bytes[..length])
+            .map_err(|e| save_err!("String at offset {} is not valid utf8: {}", offset, e) )
     }
 
-    pub fn read_string_array_hashmap(&mut self) -> fnv::FnvHashMap<String, Vec<u8>> {
-        let mut out = fnv::FnvHashMap::default();
-        let count = self.read_u32() as usize;
-        if count == 0 {
-            return out;
-        }
-
-        for _ in 0..count {
-            let key = self.read_str().to_string();
-            let value: Vec<u8> = self.read_array().to_vec();
-            out.insert(key, value);
-        }
-
-        out
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        self.try_read::<u32>()
     }
 
-    fn read_u32(&mut self) -> u32 {
+    /// Returns the next `size` bytes and moves the read offset after them
+    fn read_bytes(&mut self, size: usize) -> Result<&'a [u8], Error> {
         let offset = self.data_offset;
-        let value = u32::read_from_bytes(&self.data[offset..offset+U32_SIZE]).unwrap();
-        self.data_offset += U32_SIZE;
-        value
-    }
+        let data: &'a [u8] = self.data;
+        let bytes = offset.checked_add(size)
+            .and_then(|end| data.get(offset..end) )
+            .ok_or_else(|| save_err!("Reading {} bytes at offset {} would go outside the save ({} bytes)", size, offset, data.len()) )?;
 
-    fn remaining_size(&self) -> usize {
-        self.data[self.data_offset..].len()
+        self.data_offset += size;
+
+        Ok(bytes)
     }
+
 }
+
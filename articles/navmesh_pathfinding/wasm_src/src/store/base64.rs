@@ -0,0 +1,61 @@
+//! Standard base64 (RFC 4648, with padding) so saves can be kept in text storages like the browser localStorage
+use crate::error::Error;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const PADDING: u8 = b'=';
+
+pub fn encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let value = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | (b[2] as u32);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (value >> (18 - 6 * i)) & 0x3F;
+                text.push(ALPHABET[index as usize] as char);
+            } else {
+                text.push(PADDING as char);
+            }
+        }
+    }
+    text
+}
+
+pub fn decode(text: &str) -> Result<Vec<u8>, Error> {
+    let text = text.trim().as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return Err(save_err!("Base64 text length ({}) is not a multiple of 4", text.len()));
+    }
+
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3);
+    for (chunk_index, chunk) in text.chunks(4).enumerate() {
+        let is_last = (chunk_index + 1) * 4 == text.len();
+        let padding = chunk.iter().rev().take_while(|&&c| c == PADDING ).count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return Err(save_err!("Invalid base64 padding at offset {}", chunk_index * 4));
+        }
+
+        let mut value = 0u32;
+        for (i, &c) in chunk[..4-padding].iter().enumerate() {
+            let sextet = decode_char(c)
+                .ok_or_else(|| save_err!("Invalid base64 character {:?} at offset {}", c as char, chunk_index * 4 + i) )?;
+            value |= (sextet as u32) << (18 - 6 * i);
+        }
+
+        let decoded = value.to_be_bytes();
+        bytes.extend_from_slice(&decoded[1..4-padding]);
+    }
+
+    Ok(bytes)
+}
+
+fn decode_char(c: u8) -> Option<u8> {
+    match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    }
+}
@@ -0,0 +1,184 @@
+//! LZ4 block compression and CRC32 for the save blobs.
+//! The compressor is a simple greedy one (single hash table, no lazy matching). It's fast enough
+//! to run on every hot reload, and saves are mostly arrays of small structs that compress well.
+use crate::error::Error;
+
+const MIN_MATCH: usize = 4;
+/// The last literals of a block must not be part of a match (LZ4 block format)
+const LAST_LITERALS: usize = 5;
+const MF_LIMIT: usize = 12;
+const MAX_OFFSET: usize = 0xFFFF;
+const HASH_BITS: u32 = 12;
+
+pub fn compress(src: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(src.len() / 2 + 16);
+    let mut table = [0u32; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut ip = 0;
+
+    let match_limit = src.len().saturating_sub(MF_LIMIT);
+    while ip < match_limit {
+        let sequence = read_u32(src, ip);
+        let hash = (sequence.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize;
+        let candidate = table[hash] as usize;
+        table[hash] = ip as u32;
+
+        let is_match = candidate < ip
+            && ip - candidate <= MAX_OFFSET
+            && read_u32(src, candidate) == sequence;
+
+        if !is_match {
+            ip += 1;
+            continue;
+        }
+
+        let mut match_length = MIN_MATCH;
+        let match_end = src.len() - LAST_LITERALS;
+        while ip + match_length < match_end && src[candidate + match_length] == src[ip + match_length] {
+            match_length += 1;
+        }
+
+        write_sequence(&mut out, &src[anchor..ip], Some((ip - candidate, match_length)));
+        ip += match_length;
+        anchor = ip;
+    }
+
+    write_sequence(&mut out, &src[anchor..], None);
+
+    out
+}
+
+/// Decompresses a block produced by `compress`. Malformed blocks return an error, they never read or write out of bounds.
+pub fn decompress(src: &[u8], size: usize) -> Result<Vec<u8>, Error> {
+    // The declared size of a corrupted save could be anything. A LZ4 block can't expand more than 255 times.
+    let mut out: Vec<u8> = Vec::with_capacity(usize::min(size, src.len().saturating_mul(255)));
+    let mut ip = 0;
+
+    let truncated = |ip: usize| save_err!("Compressed save is truncated at offset {}", ip);
+
+    while ip < src.len() {
+        let token = src[ip];
+        ip += 1;
+
+        let literals_length = read_length(src, &mut ip, (token >> 4) as usize).ok_or_else(|| truncated(ip) )?;
+        let literals = ip.checked_add(literals_length)
+            .and_then(|end| src.get(ip..end) )
+            .ok_or_else(|| truncated(ip) )?;
+
+        if out.len() + literals.len() > size {
+            return Err(save_err!("Compressed save is larger than its declared size ({} bytes)", size));
+        }
+
+        out.extend_from_slice(literals);
+        ip += literals_length;
+
+        // The last sequence only has literals
+        if ip == src.len() {
+            break;
+        }
+
+        let offset = src.get(ip..ip+2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize )
+            .ok_or_else(|| truncated(ip) )?;
+        ip += 2;
+
+        if offset == 0 || offset > out.len() {
+            return Err(save_err!("Compressed save has an invalid match offset {} at offset {}", offset, ip - 2));
+        }
+
+        let match_length = read_length(src, &mut ip, (token & 0xF) as usize).ok_or_else(|| truncated(ip) )? + MIN_MATCH;
+        if out.len() + match_length > size {
+            return Err(save_err!("Compressed save is larger than its declared size ({} bytes)", size));
+        }
+
+        // Matches can overlap with the bytes they are copying
+        let start = out.len() - offset;
+        for i in 0..match_length {
+            out.push(out[start + i]);
+        }
+    }
+
+    if out.len() != size {
+        return Err(save_err!("Compressed save has {} bytes, but its declared size is {} bytes", out.len(), size));
+    }
+
+    Ok(out)
+}
+
+/// CRC32 (IEEE) of `data`
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut value = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            value = match value & 1 {
+                1 => 0xEDB88320 ^ (value >> 1),
+                _ => value >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = value;
+        i += 1;
+    }
+    table
+}
+
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], found_match: Option<(usize, usize)>) {
+    let literals_nibble = usize::min(literals.len(), 15) as u8;
+    let match_nibble = found_match
+        .map(|(_, length)| usize::min(length - MIN_MATCH, 15) as u8 )
+        .unwrap_or(0);
+
+    out.push((literals_nibble << 4) | match_nibble);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+
+    out.extend_from_slice(literals);
+
+    if let Some((offset, length)) = found_match {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if length - MIN_MATCH >= 15 {
+            write_length(out, length - MIN_MATCH - 15);
+        }
+    }
+}
+
+fn write_length(out: &mut Vec<u8>, mut length: usize) {
+    while length >= 255 {
+        out.push(255);
+        length -= 255;
+    }
+    out.push(length as u8);
+}
+
+fn read_length(src: &[u8], ip: &mut usize, nibble: usize) -> Option<usize> {
+    let mut length = nibble;
+    if nibble == 15 {
+        loop {
+            let byte = *src.get(*ip)?;
+            *ip += 1;
+            length = length.checked_add(byte as usize)?;
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Some(length)
+}
+
+fn read_u32(src: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([src[offset], src[offset+1], src[offset+2], src[offset+3]])
+}
@@ -0,0 +1,223 @@
+//! Tests of `#[derive(StoreLoad)]`. They live in this crate because the derived code uses `crate::store`.
+use hecs::Entity;
+use crate::error::Error;
+use super::{unpack, Schema, StoreLoad, StoreReader, StoreSection, StoreWriter};
+
+fn save<T: StoreLoad>(value: &mut T) -> Box<[u8]> {
+    let mut writer = StoreWriter::new(&[]);
+    value.store(&mut writer);
+    writer.finish(false)
+}
+
+fn load<T: StoreLoad>(bytes: &[u8]) -> Result<T, Error> {
+    let content = unpack(bytes)?;
+    let mut reader = StoreReader::new(&content, &[])?;
+    T::load(&mut reader)
+}
+
+/// Saves `value` in a section listed in the header, like `GameClient::as_bytes`
+fn save_section<T: StoreLoad>(value: &mut T) -> Box<[u8]> {
+    let mut writer = StoreWriter::new(&[StoreSection::of::<T>("value")]);
+    writer.write_section("value", |writer| value.store(writer) );
+    writer.finish(false)
+}
+
+fn load_section<T: StoreLoad>(bytes: &[u8]) -> Result<T, Error> {
+    let content = unpack(bytes)?;
+    let mut reader = StoreReader::new(&content, &[StoreSection::of::<T>("value")])?;
+    let mut value = None;
+    reader.read_sections(|reader, tag| {
+        if tag == super::tag("value") {
+            value = Some(T::load(reader)?);
+            return Ok(true);
+        }
+        Ok(false)
+    })?;
+
+    value.ok_or_else(|| save_err!("Missing section"))
+}
+
+fn round_trip<T: StoreLoad>(value: &mut T) -> T {
+    load(&save(value)).unwrap_or_else(|e| panic!("Failed to load the save: {}", e))
+}
+
+/// One field of each kind inferred from the type name
+#[derive(Default, PartialEq, Debug, StoreLoad)]
+struct AllKinds {
+    value: [f32; 2],
+    flag: bool,
+    option: Option<u32>,
+    entity: Option<Entity>,
+    array: Vec<u16>,
+    string: String,
+}
+
+#[derive(Default, PartialEq, Debug, StoreLoad)]
+struct Inner {
+    count: u32,
+    name: String,
+}
+
+#[derive(Default, PartialEq, Debug, StoreLoad)]
+#[store(after_load = Outer::after_load)]
+struct Outer {
+    #[store(nested)]
+    inner: Inner,
+    #[store(skip)]
+    cache: u32,
+    #[store(skip)]
+    name_length: usize,
+}
+
+impl Outer {
+    fn after_load(&mut self) -> Result<(), Error> {
+        if self.inner.count > 100 {
+            return Err(save_err!("Too many things"));
+        }
+
+        self.name_length = self.inner.name.len();
+        Ok(())
+    }
+}
+
+#[derive(Default, StoreLoad)]
+struct SettingsV1 {
+    size: u32,
+}
+
+#[derive(Default, StoreLoad)]
+struct SettingsV2 {
+    size: u32,
+    #[store(default = default_scale)]
+    scale: f32,
+    #[store(default = default_scale)]
+    other_scale: f32,
+}
+
+fn default_scale() -> f32 {
+    2.5
+}
+
+#[test]
+fn every_field_kind_round_trips() {
+    let mut world = hecs::World::new();
+    let mut value = AllKinds {
+        value: [1.5, -2.0],
+        flag: true,
+        option: Some(7),
+        entity: Some(world.spawn(())),
+        array: vec![1, 2, 3],
+        string: "Hello".to_string(),
+    };
+
+    assert_eq!(round_trip(&mut value), value);
+    assert_eq!(round_trip(&mut AllKinds::default()), AllKinds::default());
+}
+
+#[test]
+fn nested_skip_and_after_load() {
+    let mut value = Outer {
+        inner: Inner { count: 3, name: "four".to_string() },
+        cache: 42,
+        name_length: 0,
+    };
+
+    let loaded = round_trip(&mut value);
+    assert_eq!(loaded.inner, value.inner);
+    assert_eq!(loaded.cache, 0, "Skipped fields must keep their default value");
+    assert_eq!(loaded.name_length, 4, "after_load must be called once the struct is loaded");
+
+    value.inner.count = 101;
+    assert!(load::<Outer>(&save(&mut value)).is_err(), "Errors of after_load must be returned");
+}
+
+#[test]
+fn missing_fields_use_their_default() {
+    let bytes = save(&mut SettingsV1 { size: 12 });
+    let loaded: SettingsV2 = load(&bytes).unwrap();
+    assert_eq!(loaded.size, 12);
+    assert_eq!(loaded.scale, 2.5);
+    assert_eq!(loaded.other_scale, 2.5);
+
+    // Fields found in the save keep their saved value, and unknown fields are skipped
+    let bytes = save(&mut SettingsV2 { size: 3, scale: 1.0, other_scale: 0.5 });
+    let loaded: SettingsV2 = load(&bytes).unwrap();
+    assert_eq!([loaded.scale, loaded.other_scale], [1.0, 0.5]);
+    assert_eq!(load::<SettingsV1>(&bytes).unwrap().size, 3);
+}
+
+#[derive(Default, StoreLoad)]
+struct WideV1 {
+    value: [f32; 4],
+}
+
+#[derive(Default, StoreLoad)]
+struct WideV2 {
+    value: f32,
+}
+
+#[test]
+fn changed_schema_is_rejected() {
+    let bytes = save_section(&mut SettingsV1 { size: 12 });
+    assert_eq!(load_section::<SettingsV1>(&bytes).unwrap().size, 12);
+    assert!(load_section::<SettingsV2>(&bytes).is_err(), "A save made with another schema must not be loaded");
+}
+
+#[test]
+fn field_with_a_new_type_is_rejected() {
+    // Without the schema check, a field keeping its name must still not be read as a prefix of its old value
+    let bytes = save(&mut WideV1 { value: [1.0, 2.0, 3.0, 4.0] });
+    assert!(load::<WideV2>(&bytes).is_err());
+
+    let bytes = save(&mut WideV2 { value: 1.0 });
+    assert!(load::<WideV1>(&bytes).is_err());
+}
+
+#[test]
+fn schema_hash_follows_the_fields() {
+    #[derive(Default, StoreLoad)]
+    struct Base { a: u32, b: u32 }
+
+    #[derive(Default, StoreLoad)]
+    struct Same { a: u32, b: u32 }
+
+    #[derive(Default, StoreLoad)]
+    struct Renamed { a: u32, c: u32 }
+
+    #[derive(Default, StoreLoad)]
+    struct Added { a: u32, b: u32, c: u32 }
+
+    #[derive(Default, StoreLoad)]
+    struct Resized { a: u32, b: u64 }
+
+    #[derive(Default, StoreLoad)]
+    struct Array { a: u32, b: Vec<u32> }
+
+    #[derive(Default, StoreLoad)]
+    struct ArrayResized { a: u32, b: Vec<[u32; 2]> }
+
+    #[derive(Default, StoreLoad)]
+    struct OptionBase { a: Option<u32> }
+
+    #[derive(Default, StoreLoad)]
+    struct OptionResized { a: Option<u64> }
+
+    #[derive(Default, StoreLoad)]
+    struct Skipped { a: u32, b: u32, #[store(skip)] _c: u32 }
+
+    #[derive(Default, StoreLoad)]
+    struct NestedBase { #[store(nested)] inner: Base }
+
+    #[derive(Default, StoreLoad)]
+    struct NestedRenamed { #[store(nested)] inner: Renamed }
+
+    let base = Schema::of::<Base>();
+    assert_eq!(Schema::of::<Same>(), base);
+    assert_eq!(Schema::of::<Skipped>(), base, "Skipped fields are not part of the schema");
+    assert_ne!(Schema::of::<Renamed>(), base);
+    assert_ne!(Schema::of::<Added>(), base);
+    assert_ne!(Schema::of::<Resized>(), base);
+    assert_ne!(Schema::of::<ArrayResized>(), Schema::of::<Array>(), "Schema of arrays must depend on their values");
+    assert_ne!(Schema::of::<OptionResized>(), Schema::of::<OptionBase>(), "Schema of options must depend on their value");
+    assert_ne!(Schema::of::<NestedRenamed>(), Schema::of::<NestedBase>(), "Nested schemas are part of the parent schema");
+}
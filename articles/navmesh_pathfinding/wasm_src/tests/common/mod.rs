@@ -0,0 +1,25 @@
+//! Helpers shared by the native tests
+// Each test binary only uses some of the helpers
+#![allow(dead_code)]
+
+use navmesh_pathfinding_demo::{native, GameClient, GameClientInit};
+
+/// Same values as the engine, see `GameInterface.start` in `ts_src/game_interface.ts`
+pub fn client_init() -> GameClientInit {
+    let mut init = GameClientInit::new();
+    init.view_size(1280.0, 720.0);
+
+    if let Err(e) = native::load_assets(&mut init, &native::demo_dir()) {
+        panic!("{}", e);
+    }
+
+    init
+}
+
+pub fn init_client() -> GameClient {
+    init_client_with(client_init())
+}
+
+pub fn init_client_with(init: GameClientInit) -> GameClient {
+    GameClient::initialize(init).expect("Failed to initialize the game client")
+}
@@ -0,0 +1,65 @@
+//! The output of the first frame of each demo state is compared with the dumps in `tests/golden`.
+//! Run with `UPDATE_GOLDEN=1` to write the dumps again after an intended change of the output, and review the diff.
+#![cfg(feature = "gui")]
+mod common;
+
+use std::path::PathBuf;
+use navmesh_pathfinding_demo::native;
+
+fn golden_path(state: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{state}.txt"))
+}
+
+fn first_frame_dump(state: &str) -> String {
+    let mut client = common::init_client();
+    assert!(native::init_demo_state(&mut client, state), "Unknown demo state {:?}", state);
+    client.update(0.0);
+    native::dump_output(&client)
+}
+
+#[test]
+fn output_matches_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for state in native::DEMO_STATES {
+        let path = golden_path(state);
+        let dump = first_frame_dump(state);
+
+        if update {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &dump).unwrap();
+            continue;
+        }
+
+        let golden = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read {:?} ({}). Run with UPDATE_GOLDEN=1 to create it.", path, e));
+
+        // Git may convert the line endings of the golden files
+        let golden = golden.replace("\r\n", "\n");
+        if golden == dump {
+            continue;
+        }
+
+        let line = golden.lines().zip(dump.lines())
+            .position(|(expected, actual)| expected != actual )
+            .unwrap_or_else(|| usize::min(golden.lines().count(), dump.lines().count()) );
+
+        failures.push(format!(
+            "{}: first difference at line {}\n  expected: {}\n  actual:   {}",
+            state,
+            line + 1,
+            golden.lines().nth(line).unwrap_or("<end of file>"),
+            dump.lines().nth(line).unwrap_or("<end of file>"),
+        ));
+    }
+
+    assert!(failures.is_empty(), "Output doesn't match the golden files. Run with UPDATE_GOLDEN=1 if the change is intended.\n{}", failures.join("\n"));
+}
+
+#[test]
+fn output_is_deterministic() {
+    for state in native::DEMO_STATES {
+        assert!(first_frame_dump(state) == first_frame_dump(state), "Output of {:?} changes between runs", state);
+    }
+}
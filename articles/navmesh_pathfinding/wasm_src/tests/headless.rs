@@ -0,0 +1,49 @@
+//! The headless runner starts the client from the files in `assets/` and sends the scripted inputs
+use std::process::Command;
+
+const SCRIPT: &str = "
+# Click in the scene
+2 mouse_inside true
+2 mouse_position 300 200
+3 mouse_button 0 pressed
+4 mouse_button 0 released
+";
+
+#[test]
+fn headless_runs_a_script() {
+    let script_path = std::env::temp_dir().join(format!("navmesh_headless_{}.txt", std::process::id()));
+    std::fs::write(&script_path, SCRIPT).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_headless"))
+        .args(["--frames", "6", "--dump", "all", "--script"])
+        .arg(&script_path)
+        .output()
+        .expect("Failed to run the headless runner");
+
+    let _ = std::fs::remove_file(&script_path);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "Headless runner failed: {}", stderr);
+
+    assert_eq!(stdout.matches("== frame").count(), 6);
+    assert!(stdout.contains("UpdateTerrain"), "First frame must upload the terrain:\n{}", stdout);
+    assert!(stdout.contains("DrawSprites"), "Sprites are never drawn:\n{}", stdout);
+}
+
+#[test]
+fn headless_rejects_invalid_scripts() {
+    let script_path = std::env::temp_dir().join(format!("navmesh_headless_invalid_{}.txt", std::process::id()));
+    std::fs::write(&script_path, "1 jump 10\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_headless"))
+        .arg("--script")
+        .arg(&script_path)
+        .output()
+        .expect("Failed to run the headless runner");
+
+    let _ = std::fs::remove_file(&script_path);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown command \"jump\""));
+}
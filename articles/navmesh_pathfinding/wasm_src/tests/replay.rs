@@ -0,0 +1,128 @@
+//! Input recordings replay the same frames as the recorded session
+mod common;
+
+use navmesh_pathfinding_demo::{native, GameClient};
+
+const FRAME_TIME: f64 = 1000.0 / 60.0;
+
+/// Offset of the seed in the recording header: magic, version, view size, pixel ratio, max texture size
+const SEED_OFFSET: usize = 24;
+
+/// Records a session that selects things, drags the mouse, uses the keyboard and restores a snapshot
+fn record_session() -> (Box<[u8]>, u64) {
+    let mut init = common::client_init();
+    init.record_inputs(true);
+    let mut client = common::init_client_with(init);
+
+    client.update_mouse_inside(true);
+    for frame in 0..120 {
+        let t = frame as f32;
+        client.update_mouse_position(100.0 + t * 4.0, 150.0 + t * 3.0);
+
+        match frame {
+            10 | 50 => client.update_mouse_buttons(0, true),
+            11 | 51 => client.update_mouse_buttons(0, false),
+            30 => client.update_keys("KeyD", true, false),
+            31 => client.update_keys("KeyD", false, false),
+            40 => client.update_mouse_wheel(0.0, -120.0),
+            60 => client.resize(1024, 600, 2.0),
+            _ => {}
+        }
+
+        if frame == 70 {
+            let snapshot = client.snapshot();
+            client.update_mouse_buttons(2, true);
+            assert!(client.restore(&snapshot));
+        }
+
+        client.update(frame as f64 * FRAME_TIME);
+    }
+
+    let recording = client.recording().expect("Client must record its inputs");
+    (recording, client.state_hash())
+}
+
+fn replay_all(recording: Box<[u8]>) -> GameClient {
+    let mut client = GameClient::replay(common::client_init(), recording).expect("Failed to start the replay");
+    let mut frames = 0;
+    while !client.replay_finished() {
+        // Inputs from the engine must be ignored during a replay
+        client.update_mouse_position(5.0, 5.0);
+        client.update(99999.0);
+
+        frames += 1;
+        assert!(frames <= 120, "Replay has more frames than the recording");
+    }
+
+    assert_eq!(frames, 120);
+    client
+}
+
+#[test]
+fn replay_matches_the_recording() {
+    let (recording, expected_hash) = record_session();
+
+    let mut client = replay_all(recording);
+    assert!(client.replay_checkpoints() > 0, "Recording has no checkpoints");
+    assert_eq!(client.replay_mismatches(), 0);
+    assert_eq!(client.state_hash(), expected_hash, "Final state of the replay doesn't match the recorded session");
+}
+
+#[test]
+fn replay_starts_from_the_demo_state() {
+    for state in native::DEMO_STATES {
+        let mut init = common::client_init();
+        init.record_inputs(true);
+        let mut client = common::init_client_with(init);
+        assert!(native::init_demo_state(&mut client, state));
+
+        for frame in 0..65 {
+            client.update(frame as f64 * FRAME_TIME);
+        }
+
+        let recording = client.recording().unwrap();
+        let mut replay = GameClient::replay(common::client_init(), recording).expect("Failed to start the replay");
+        while !replay.replay_finished() {
+            replay.update(0.0);
+        }
+
+        assert!(replay.replay_checkpoints() > 0);
+        assert_eq!(replay.replay_mismatches(), 0, "Replay of {:?} diverged from the recording", state);
+        assert_eq!(replay.state_hash(), client.state_hash());
+    }
+}
+
+#[test]
+fn replay_detects_divergences() {
+    let (recording, _) = record_session();
+
+    let mut modified = recording.to_vec();
+    modified[SEED_OFFSET] ^= 0xFF;
+
+    let client = replay_all(modified.into_boxed_slice());
+    // The restored snapshot was recorded with the original seed, so only the checkpoints before it diverge
+    assert!(client.replay_mismatches() > 0, "A different seed must change the checkpoints");
+}
+
+#[test]
+fn invalid_recordings_never_panic() {
+    let (recording, _) = record_session();
+    let init = common::client_init;
+
+    assert!(GameClient::replay(init(), Box::from(&b"NMPD"[..])).is_none());
+
+    let step = usize::max(1, recording.len() / 50);
+    for length in (0..recording.len()).step_by(step) {
+        let Some(mut client) = GameClient::replay(init(), Box::from(&recording[..length])) else {
+            continue;
+        };
+
+        let mut frames = 0;
+        while !client.replay_finished() && frames < 200 {
+            client.update(0.0);
+            frames += 1;
+        }
+
+        assert!(client.replay_finished(), "Replay of a recording truncated at {} bytes never finished", length);
+    }
+}
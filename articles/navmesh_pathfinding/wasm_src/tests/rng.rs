@@ -0,0 +1,43 @@
+//! The random number generator must produce the same values on every target and keep its sequence across saves
+mod common;
+
+use navmesh_pathfinding_demo::{native, GameClient, Rng, AABB};
+
+const SEED: u32 = 1234;
+
+#[test]
+fn sequence_is_pinned() {
+    let mut rng = Rng::new(SEED);
+    let values: Vec<u32> = (0..8).map(|_| rng.next_u32() ).collect();
+    assert_eq!(values, [1927626933, 2777857285, 1362201715, 1134824276, 1166963396, 1362470428, 3154681131, 3481838373]);
+
+    let mut rng = Rng::new(SEED);
+    assert_eq!(rng.range_u32(10, 20), 14);
+    assert_eq!(rng.range_i32(-20, -10), -14);
+    assert_eq!(rng.range_f32(0.0, 1.0).to_bits(), 0x3ea26318);
+
+    let point = rng.point_in_aabb(AABB { left: 10.0, top: 20.0, right: 30.0, bottom: 40.0 });
+    assert_eq!([point.x.to_bits(), point.y.to_bits()], [0x41748d0e, 0x41cb7907]);
+
+    let mut values = [0, 1, 2, 3, 4, 5, 6, 7];
+    rng.shuffle(&mut values);
+    assert_eq!(values, [6, 3, 1, 7, 0, 4, 5, 2]);
+}
+
+#[test]
+fn sequence_continues_after_save() {
+    let mut init = common::client_init();
+    init.seed(SEED);
+    let mut client = common::init_client_with(init);
+    for _ in 0..5 {
+        native::rng(&mut client).next_u32();
+    }
+
+    let bytes = client.as_bytes(false);
+    let mut loaded = GameClient::from_bytes(&bytes, &common::client_init()).expect("Failed to load the client");
+    assert_eq!(native::rng(&mut loaded).seed(), SEED);
+
+    for _ in 0..16 {
+        assert_eq!(native::rng(&mut loaded).next_u32(), native::rng(&mut client).next_u32());
+    }
+}
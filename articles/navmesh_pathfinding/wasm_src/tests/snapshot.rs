@@ -0,0 +1,74 @@
+//! Snapshots made by `GameClient::snapshot` are restored in another client and survive the text encoding
+mod common;
+
+use navmesh_pathfinding_demo::{snapshot_from_text, snapshot_to_text};
+
+const FRAME_TIME: f64 = 1000.0 / 60.0;
+
+#[test]
+fn snapshot_restores_in_a_new_client() {
+    let mut client = common::init_client();
+    for frame in 0..10 {
+        client.update(frame as f64 * FRAME_TIME);
+    }
+
+    let snapshot = client.snapshot();
+
+    // The restored client keeps its own clock, so it must be at the same time to compare the animations.
+    // A different seed makes sure its data is replaced.
+    let mut init = common::client_init();
+    init.seed(0xBAD5EED);
+    let mut restored = common::init_client_with(init);
+    for frame in 0..10 {
+        restored.update(frame as f64 * FRAME_TIME);
+    }
+
+    assert!(restored.snapshot() != snapshot);
+
+    assert!(restored.restore(&snapshot), "Failed to restore the snapshot");
+
+    // Restoring flags the terrain for upload, so both clients are compared after a frame
+    let time = 10.0 * FRAME_TIME;
+    client.update(time);
+    restored.update(time);
+    assert!(client.snapshot() == restored.snapshot(), "Restored client doesn't match the snapshot");
+}
+
+#[test]
+fn invalid_snapshot_keeps_the_client() {
+    let mut client = common::init_client();
+    client.update(0.0);
+    let before = client.snapshot();
+
+    let mut truncated = before.to_vec();
+    truncated.truncate(before.len() / 2);
+
+    assert!(!client.restore(b"NMPD"));
+    assert!(!client.restore(&truncated));
+    assert!(client.snapshot() == before, "A failed restore modified the client");
+}
+
+#[test]
+fn snapshot_text_round_trip() {
+    let mut client = common::init_client();
+    client.update(0.0);
+    let snapshot = client.snapshot();
+
+    let text = snapshot_to_text(&snapshot);
+    assert_eq!(snapshot_from_text(&text).as_deref(), Some(&snapshot[..]));
+
+    // Every padding length
+    for length in 0..8 {
+        let bytes: Vec<u8> = (0..length).map(|i| (i * 97 + 13) as u8).collect();
+        let text = snapshot_to_text(&bytes);
+        assert_eq!(text.len() % 4, 0);
+        assert_eq!(snapshot_from_text(&text).as_deref(), Some(&bytes[..]));
+    }
+
+    assert_eq!(snapshot_to_text(b"foobar"), "Zm9vYmFy");
+    assert_eq!(snapshot_to_text(b"fooba"), "Zm9vYmE=");
+
+    for invalid in ["Zm9", "Zm9v!mFy", "Zm=vYmFy", "Z===", "Zg==Zg=="] {
+        assert!(snapshot_from_text(invalid).is_none(), "{:?} was decoded", invalid);
+    }
+}
@@ -0,0 +1,132 @@
+//! Truncated and corrupted saves must be rejected with an error, never with a panic
+mod common;
+
+use navmesh_pathfinding_demo::GameClient;
+
+const FRAME_TIME: f64 = 1000.0 / 60.0;
+
+/// Xorshift, so the corrupted saves are the same on every run
+struct Fuzzer(u32);
+
+impl Fuzzer {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    fn index(&mut self, len: usize) -> usize {
+        self.next() as usize % len
+    }
+}
+
+/// Magic, version, flags and size
+const HEADER_SIZE: usize = 16;
+
+fn saved_client(compress: bool) -> Box<[u8]> {
+    let mut client = common::init_client();
+    for frame in 0..10 {
+        client.update(frame as f64 * FRAME_TIME);
+    }
+
+    client.as_bytes(compress)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { 0xEDB88320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Updates the checksum footer so the corrupted content reaches the decompression and the reader
+fn restamp_checksum(bytes: &mut [u8]) {
+    let content_size = bytes.len() - 4;
+    let crc = crc32(&bytes[..content_size]);
+    bytes[content_size..].copy_from_slice(&crc.to_le_bytes());
+}
+
+#[test]
+fn valid_save_loads() {
+    for compress in [false, true] {
+        let bytes = saved_client(compress);
+        let mut client = match GameClient::from_bytes(&bytes, &common::client_init()) {
+            Ok(client) => client,
+            Err(e) => panic!("{}", e),
+        };
+
+        // Entities keep their ids and components
+        assert!(client.as_bytes(compress) == bytes, "Saving a reloaded client must produce the same save");
+    }
+}
+
+#[test]
+fn compressed_save_is_smaller() {
+    let raw = saved_client(false);
+    let compressed = saved_client(true);
+    assert!(compressed.len() < raw.len(), "Compressed save ({} bytes) is not smaller than the raw save ({} bytes)", compressed.len(), raw.len());
+}
+
+#[test]
+fn truncated_saves_are_rejected() {
+    let init = common::client_init();
+    for compress in [false, true] {
+        let bytes = saved_client(compress);
+        let step = usize::max(1, bytes.len() / 512);
+        for length in (0..bytes.len()).step_by(step) {
+            assert!(GameClient::from_bytes(&bytes[..length], &init).is_err(), "Save truncated at {} bytes was accepted", length);
+        }
+    }
+}
+
+#[test]
+fn corrupted_saves_are_rejected() {
+    let init = common::client_init();
+    let bytes = saved_client(false);
+    let mut fuzzer = Fuzzer(0x9E37_79B9);
+
+    for _ in 0..100 {
+        let mut corrupted = bytes.to_vec();
+        let index = fuzzer.index(corrupted.len());
+        corrupted[index] ^= 1 << fuzzer.index(8);
+        assert!(GameClient::from_bytes(&corrupted, &init).is_err(), "Save with a flipped bit at {} was accepted", index);
+    }
+}
+
+#[test]
+fn corrupted_content_never_panics() {
+    let init = common::client_init();
+    let mut fuzzer = Fuzzer(0x1234_5678);
+
+    for compress in [false, true] {
+        let bytes = saved_client(compress);
+        let content_size = bytes.len() - HEADER_SIZE - 4;
+
+        for _ in 0..300 {
+            let mut corrupted = bytes.to_vec();
+            let flips = 1 + fuzzer.index(4);
+            for _ in 0..flips {
+                let index = HEADER_SIZE + fuzzer.index(content_size);
+                corrupted[index] ^= 1 << fuzzer.index(8);
+            }
+
+            // Corrupting a value (ex: a position) can still produce a valid save
+            restamp_checksum(&mut corrupted);
+            let _ = GameClient::from_bytes(&corrupted, &init);
+        }
+
+        // Random u32 values in the length fields are the most likely way to read outside the save
+        for _ in 0..300 {
+            let mut corrupted = bytes.to_vec();
+            let index = HEADER_SIZE + fuzzer.index(content_size - 4);
+            corrupted[index..index+4].copy_from_slice(&fuzzer.next().to_le_bytes());
+            restamp_checksum(&mut corrupted);
+            let _ = GameClient::from_bytes(&corrupted, &init);
+        }
+    }
+}
@@ -121,7 +121,7 @@ fn must_reload_file(event: &notify::event::Event) -> bool {
     };
 
     let extension = path.extension().and_then(|ext| ext.to_str() ).unwrap_or("");
-    if !ASSETS_EXTENSIONS_TO_RELOAD.iter().any(|&ext| extension == ext ) {
+    if !ASSETS_EXTENSIONS_TO_RELOAD.contains(&extension) {
         return false;
     }
 
@@ -172,10 +172,10 @@ fn watch_files(assets: &SharedAssetsCollection) {
                 }
             }
 
-            if accumulate.len() > 0 {
+            if !accumulate.is_empty() {
                 let mut assets = assets_guard.lock().unwrap();
                 for (local_path, web_path) in accumulate.iter() {
-                    if let Some(source) = assets.get_mut(&web_path) {
+                    if let Some(source) = assets.get_mut(web_path) {
                         match source {
                             FileType::Text(value) => { *value = read_to_string(local_path).unwrap_or("".to_string()); }
                             FileType::Bin(value) => { *value = read(local_path).unwrap_or(Vec::new()); }
@@ -209,7 +209,7 @@ fn handle_websocket(assets: &SharedAssetsCollection, request: &Request) -> Optio
         }
     };
 
-    let assets_guard = Arc::clone(&assets);
+    let assets_guard = Arc::clone(assets);
 
     ::std::thread::spawn(move || {
         let initial_connection_timeout = Duration::from_millis(1000);
@@ -235,7 +235,7 @@ fn handle_websocket(assets: &SharedAssetsCollection, request: &Request) -> Optio
                 let cmd = format!("{{ \"name\": \"FILE_CHANGED\", \"data\": {:?} }}", path);
                 
                 //println!("{:?}", cmd);
-                if let Err(_) = connection.send_text(&cmd) {
+                if connection.send_text(&cmd).is_err() {
                     break 'outer;
                 }
             }
@@ -289,7 +289,7 @@ fn main() {
     rouille::start_server("localhost:8001", move |request| {
         match request.method() {
             "GET" => {
-                if let Some(response) = handle_websocket(&assets, &request) {
+                if let Some(response) = handle_websocket(&assets, request) {
                     return response;
                 }
 